@@ -1,7 +1,7 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 
-use mylib::evaluator::Evaluator;
-use mylib::{lexer, parser};
+use mylib::evaluator::{Evaluator, Signal};
+use mylib::{lexer, parser, token};
 
 fn bm1(c: &mut Criterion) {
     c.bench_function("My Answer", |b| {
@@ -42,12 +42,31 @@ fn bm1(c: &mut Criterion) {
             }
 
             match evaluator.eval_program(program) {
-                Some(obj) => println!("{}", &*obj.string()),
-                None => panic!(),
+                Signal::Value(obj) => println!("{}", &*obj.string()),
+                _ => panic!(),
             }
         })
     });
 }
 
-criterion_group!(benches, bm1);
+fn bm_lex_large_input(c: &mut Criterion) {
+    let chunk = "let row = [1, 2.5, \"name\", true, { \"key\": 10 }];
+let total = fn(x, y) { x + y * 2 - 1 / 3; };
+if (total(row[0], 3) == 10) { puts(\"ten\"); } else { puts(\"other\"); }
+";
+    let input = chunk.repeat(50 * 1024 / chunk.len() + 1);
+
+    c.bench_function("Lex 50KB input", |b| {
+        b.iter(|| {
+            let mut l = lexer::Lexer::new(&input);
+            let mut count = 0;
+            while l.next_token().token_type != token::TokenType::EOF {
+                count += 1;
+            }
+            count
+        })
+    });
+}
+
+criterion_group!(benches, bm1, bm_lex_large_input);
 criterion_main!(benches);
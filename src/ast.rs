@@ -1,3 +1,4 @@
+use super::token;
 use std::fmt;
 
 #[derive(PartialEq)]
@@ -5,16 +6,6 @@ pub struct Program {
     pub statements: Vec<Statement>,
 }
 
-impl Program {
-    pub fn need_next(&self) -> bool {
-        if let Some(stmt) = self.statements.last() {
-            return stmt.need_next();
-        } else {
-            return false;
-        };
-    }
-}
-
 impl fmt::Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut s = "".to_string();
@@ -28,21 +19,54 @@ impl fmt::Display for Program {
 
 #[derive(Clone, PartialEq)]
 pub enum Statement {
-    LetStatement { name: Expression, value: Expression },
-    ReturnStatement { return_value: Expression },
-    ExpressionStatement { expression: Expression },
-    BlockStatement { statements: Vec<Statement> },
+    LetStatement {
+        name: Expression,
+        value: Expression,
+        span: token::Span,
+    },
+    ReturnStatement {
+        return_value: Expression,
+        span: token::Span,
+    },
+    ExpressionStatement {
+        expression: Expression,
+        span: token::Span,
+    },
+    BlockStatement {
+        statements: Vec<Statement>,
+        span: token::Span,
+    },
+    BreakStatement {
+        span: token::Span,
+    },
+    ContinueStatement {
+        span: token::Span,
+    },
+    // `fn name(..) {..}`, bound before the rest of its block runs.
+    FunctionDeclaration {
+        name: String,
+        parameters: Vec<Parameter>,
+        body: Box<Statement>,
+        span: token::Span,
+    },
+    ThrowStatement {
+        value: Expression,
+        span: token::Span,
+    },
 }
 
+#[allow(dead_code)]
 impl Statement {
-    pub fn need_next(&self) -> bool {
+    pub fn span(&self) -> &token::Span {
         match self {
-            Statement::LetStatement { name: _, value } => value.need_next(),
-            Statement::ReturnStatement { return_value } => return_value.need_next(),
-            Statement::ExpressionStatement { expression } => expression.need_next(),
-            Statement::BlockStatement { statements } => {
-                statements.iter().any(|statement| statement.need_next())
-            }
+            Statement::LetStatement { span, .. }
+            | Statement::ReturnStatement { span, .. }
+            | Statement::ExpressionStatement { span, .. }
+            | Statement::BlockStatement { span, .. }
+            | Statement::BreakStatement { span }
+            | Statement::ContinueStatement { span }
+            | Statement::FunctionDeclaration { span, .. }
+            | Statement::ThrowStatement { span, .. } => span,
         }
     }
 }
@@ -50,20 +74,34 @@ impl Statement {
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Statement::LetStatement { name, value } => {
+            Statement::LetStatement { name, value, .. } => {
                 return write!(f, "let {} = {};", name, value)
             }
-            Statement::ReturnStatement { return_value } => {
+            Statement::ReturnStatement { return_value, .. } => {
                 return write!(f, "return {};", return_value);
             }
-            Statement::ExpressionStatement { expression } => return write!(f, "{}", expression),
-            Statement::BlockStatement { statements } => {
+            Statement::ExpressionStatement { expression, .. } => {
+                return write!(f, "{}", expression)
+            }
+            Statement::BlockStatement { statements, .. } => {
                 let mut s = "".to_string();
                 for stmt in statements {
                     s += &format!("\t{}\r\n", stmt);
                 }
                 return write!(f, "{{\r\n{}}}", s);
             }
+            Statement::BreakStatement { .. } => write!(f, "break;"),
+            Statement::ContinueStatement { .. } => write!(f, "continue;"),
+            Statement::FunctionDeclaration {
+                name,
+                parameters,
+                body,
+                ..
+            } => {
+                let parameters: Vec<String> = parameters.iter().map(|p| p.to_string()).collect();
+                write!(f, "fn {}({}) {}", name, parameters.join(", "), body)
+            }
+            Statement::ThrowStatement { value, .. } => write!(f, "throw {};", value),
         }
     }
 }
@@ -72,64 +110,133 @@ impl fmt::Display for Statement {
 pub enum Expression {
     Identifier {
         value: String,
+        span: token::Span,
     },
     IntegerLiteral {
         value: i64,
+        span: token::Span,
+    },
+    FloatLiteral {
+        value: f64,
+        span: token::Span,
     },
     StringLiteral {
         value: String,
+        span: token::Span,
     },
     PrefixExpression {
         operator: String,
         right: Box<Expression>,
+        span: token::Span,
     },
     InfixExpression {
         left: Box<Expression>,
         operator: String,
         right: Box<Expression>,
+        span: token::Span,
     },
     AssignExpression {
         left: Box<Expression>,
+        operator: String,
         right: Box<Expression>,
+        span: token::Span,
     },
     Boolean {
         value: bool,
+        span: token::Span,
     },
     ArrayLiteral {
         elements: Vec<Expression>,
+        span: token::Span,
     },
     IndexExpression {
         left: Box<Expression>,
         index: Box<Expression>,
+        span: token::Span,
     },
     IfExpression {
         condition: Box<Expression>,
         consequence: Box<Statement>,
         alternative: Option<Box<Statement>>,
+        span: token::Span,
     },
     WhileExpression {
         condition: Box<Expression>,
         consequence: Box<Statement>,
+        span: token::Span,
+    },
+    ForExpression {
+        variables: Vec<Expression>,
+        iterable: Box<Expression>,
+        body: Box<Statement>,
+        span: token::Span,
     },
     FunctionLiteral {
-        parameters: Vec<Expression>,
+        parameters: Vec<Parameter>,
         body: Box<Statement>,
+        span: token::Span,
     },
     CallExpression {
         function: Box<Expression>,
         arguments: Vec<Expression>,
+        // `name: value` arguments, which always follow the positional ones.
+        named_arguments: Vec<(Expression, Expression)>,
+        span: token::Span,
     },
     HashLiteral {
         pairs: Vec<(Expression, Expression)>,
+        span: token::Span,
+    },
+    MatchExpression {
+        subject: Box<Expression>,
+        arms: Vec<MatchArm>,
+        span: token::Span,
+    },
+    // Only found on the left of `let` and in `match` arms.
+    ArrayPattern {
+        elements: Vec<Expression>,
+        rest: Option<Box<Expression>>,
+        span: token::Span,
+    },
+    // `{name, age: a}`: each key is an identifier naming a string key, with
+    // an optional pattern for its value. `{name}` binds the value to `name`.
+    HashPattern {
+        entries: Vec<(Expression, Option<Expression>)>,
+        span: token::Span,
+    },
+    // `try {..} catch (<pattern>) {..} finally {..}`, with at least one of
+    // the `catch` and `finally` clauses.
+    TryExpression {
+        body: Box<Statement>,
+        catch: Option<(Box<Expression>, Box<Statement>)>,
+        finally: Option<Box<Statement>>,
+        span: token::Span,
     },
-    NeedNext,
 }
 
 impl Expression {
-    fn need_next(&self) -> bool {
+    pub fn span(&self) -> &token::Span {
         match self {
-            Expression::NeedNext => true,
-            _ => false,
+            Expression::Identifier { span, .. }
+            | Expression::IntegerLiteral { span, .. }
+            | Expression::FloatLiteral { span, .. }
+            | Expression::StringLiteral { span, .. }
+            | Expression::PrefixExpression { span, .. }
+            | Expression::InfixExpression { span, .. }
+            | Expression::AssignExpression { span, .. }
+            | Expression::Boolean { span, .. }
+            | Expression::ArrayLiteral { span, .. }
+            | Expression::IndexExpression { span, .. }
+            | Expression::IfExpression { span, .. }
+            | Expression::WhileExpression { span, .. }
+            | Expression::ForExpression { span, .. }
+            | Expression::FunctionLiteral { span, .. }
+            | Expression::CallExpression { span, .. }
+            | Expression::HashLiteral { span, .. }
+            | Expression::MatchExpression { span, .. }
+            | Expression::ArrayPattern { span, .. }
+            | Expression::HashPattern { span, .. }
+            | Expression::TryExpression { span, .. } => span,
         }
     }
 }
@@ -137,24 +244,33 @@ impl Expression {
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Expression::Identifier { value } => return write!(f, "{}", value),
-            Expression::IntegerLiteral { value } => return write!(f, "{}", value),
-            Expression::StringLiteral { value } => return write!(f, "\"{}\"", value),
-            Expression::PrefixExpression { operator, right } => {
+            Expression::Identifier { value, .. } => return write!(f, "{}", value),
+            Expression::IntegerLiteral { value, .. } => return write!(f, "{}", value),
+            Expression::FloatLiteral { value, .. } => write!(f, "{:?}", value),
+            Expression::StringLiteral { value, .. } => write!(f, "{:?}", value),
+            Expression::PrefixExpression {
+                operator, right, ..
+            } => {
                 return write!(f, "({}{})", operator, right);
             }
             Expression::InfixExpression {
                 left,
                 operator,
                 right,
+                ..
             } => {
                 return write!(f, "({} {} {})", left, operator, right);
             }
-            Expression::AssignExpression { left, right } => {
-                return write!(f, "{} = {}", left, right);
+            Expression::AssignExpression {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                return write!(f, "{} {} {}", left, operator, right);
             }
-            Expression::Boolean { value } => return write!(f, "{}", value),
-            Expression::ArrayLiteral { elements } => {
+            Expression::Boolean { value, .. } => return write!(f, "{}", value),
+            Expression::ArrayLiteral { elements, .. } => {
                 let mut s = "".to_string();
                 for (i, p) in elements.iter().enumerate() {
                     if i == 0 {
@@ -165,13 +281,14 @@ impl fmt::Display for Expression {
                 }
                 return write!(f, "[{}]", s);
             }
-            Expression::IndexExpression { left, index } => {
+            Expression::IndexExpression { left, index, .. } => {
                 return write!(f, "({})[{}]", left, index);
             }
             Expression::IfExpression {
                 condition,
                 consequence,
                 alternative,
+                ..
             } => match alternative {
                 Some(alt) => return write!(f, "if ({}) {} else {}", condition, consequence, alt),
                 None => return write!(f, "if ({}) {}", condition, consequence),
@@ -179,8 +296,20 @@ impl fmt::Display for Expression {
             Expression::WhileExpression {
                 condition,
                 consequence,
+                ..
             } => return write!(f, "while ({}) {}", condition, consequence),
-            Expression::FunctionLiteral { parameters, body } => {
+            Expression::ForExpression {
+                variables,
+                iterable,
+                body,
+                ..
+            } => {
+                let variables: Vec<String> = variables.iter().map(|v| v.to_string()).collect();
+                write!(f, "for ({} in {}) {}", variables.join(", "), iterable, body)
+            }
+            Expression::FunctionLiteral {
+                parameters, body, ..
+            } => {
                 let mut s = "".to_string();
                 for (i, p) in parameters.iter().enumerate() {
                     if i == 0 {
@@ -194,6 +323,8 @@ impl fmt::Display for Expression {
             Expression::CallExpression {
                 function,
                 arguments,
+                named_arguments,
+                ..
             } => {
                 let mut s = "".to_string();
                 for (i, a) in arguments.iter().enumerate() {
@@ -203,9 +334,15 @@ impl fmt::Display for Expression {
                         s += &format!(", {}", a);
                     }
                 }
+                for (name, value) in named_arguments {
+                    if !s.is_empty() {
+                        s += ", ";
+                    }
+                    s += &format!("{}: {}", name, value);
+                }
                 return write!(f, "{}({})", function, s);
             }
-            Expression::HashLiteral { pairs } => {
+            Expression::HashLiteral { pairs, .. } => {
                 let mut s = "{ ".to_string();
                 for (i, (key, value)) in pairs.iter().enumerate() {
                     if i == 0 {
@@ -217,7 +354,83 @@ impl fmt::Display for Expression {
                 s += " }";
                 return write!(f, "{}", s);
             }
-            Expression::NeedNext => return write!(f, ""),
+            Expression::ArrayPattern { elements, rest, .. } => {
+                let mut items: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                if let Some(rest) = rest {
+                    items.push(format!("...{}", rest));
+                }
+                write!(f, "[{}]", items.join(", "))
+            }
+            Expression::MatchExpression { subject, arms, .. } => {
+                let arms: Vec<String> = arms.iter().map(|a| a.to_string()).collect();
+                write!(f, "match ({}) {{ {} }}", subject, arms.join(", "))
+            }
+            Expression::HashPattern { entries, .. } => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, pattern)| match pattern {
+                        Some(pattern) => format!("{}: {}", key, pattern),
+                        None => key.to_string(),
+                    })
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            Expression::TryExpression {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                write!(f, "try {}", body)?;
+                if let Some((parameter, block)) = catch {
+                    write!(f, " catch ({}) {}", parameter, block)?;
+                }
+                if let Some(block) = finally {
+                    write!(f, " finally {}", block)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// `<pattern> if <guard> => <body>`. The body is a block or a single
+// expression statement.
+#[derive(Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Expression,
+    pub guard: Option<Expression>,
+    pub body: Statement,
+    pub span: token::Span,
+}
+
+impl fmt::Display for MatchArm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.guard {
+            Some(guard) => write!(f, "{} if {} => {}", self.pattern, guard, self.body),
+            None => write!(f, "{} => {}", self.pattern, self.body),
+        }
+    }
+}
+
+// `x`, `x = <default>` or `...x`. Only the last parameter can be a rest
+// parameter, which collects the remaining positional arguments.
+#[derive(Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub default: Option<Expression>,
+    pub rest: bool,
+    pub span: token::Span,
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.rest {
+            return write!(f, "...{}", self.name);
+        }
+        match &self.default {
+            Some(default) => write!(f, "{} = {}", self.name, default),
+            None => write!(f, "{}", self.name),
         }
     }
 }
@@ -232,10 +445,13 @@ mod ast_tests {
             statements: vec![Statement::LetStatement {
                 name: Expression::Identifier {
                     value: "myVar".to_string(),
+                    span: token::Span::default(),
                 },
                 value: Expression::Identifier {
                     value: "anotherVar".to_string(),
+                    span: token::Span::default(),
                 },
+                span: token::Span::default(),
             }],
         };
 
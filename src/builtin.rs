@@ -1,7 +1,8 @@
+use super::evaluator::Signal;
 use super::object::*;
 use super::*;
 use std::collections::HashMap;
-use std::fs::read_to_string;
+use std::fs::{canonicalize, read_to_string};
 use std::rc::Rc;
 
 pub fn new_builtins() -> HashMap<String, Rc<Object>> {
@@ -38,34 +39,45 @@ pub fn new_builtins() -> HashMap<String, Rc<Object>> {
         String::from("puts"),
         Object::new_builtin(BuiltinFunc(8, strainer_puts)),
     );
+    builtins.insert(
+        String::from("range"),
+        Object::new_builtin(BuiltinFunc(9, strainer_range)),
+    );
     builtins
 }
 
-fn strainer_exit(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Rc<Object> {
+fn strainer_exit(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Signal {
     if args.len() > 1 {
-        return Object::new_error(format!(
-            "wrong number of arguments. got={}, want=1",
-            args.len()
+        return Signal::Throw(Object::new_error(
+            ARGUMENT_ERROR,
+            format!("wrong number of arguments. got={}, want=1", args.len()),
         ));
     }
 
     if args.len() == 0 {
-        return Rc::new(object::EXIT);
+        return Signal::Exit(0);
     }
 
     match &*args[0] {
-        Object::Integer(_) => {
-            return Rc::new(object::EXIT);
-        }
-        o => Object::new_error(format!("argument to `len` not supported, got {}", o)),
+        Object::Integer(code) => match i32::try_from(*code) {
+            Ok(code) => return Signal::Exit(code),
+            Err(_) => Signal::Throw(Object::new_error(
+                ARGUMENT_ERROR,
+                format!("exit status out of range, got {}", code),
+            )),
+        },
+        o => Signal::Throw(Object::new_error(
+            TYPE_ERROR,
+            format!("argument to `exit` not supported, got {}", o),
+        )),
     }
 }
 
-fn strainer_import(args: Vec<Rc<Object>>, eval: &mut evaluator::Evaluator) -> Rc<Object> {
+fn strainer_import(args: Vec<Rc<Object>>, eval: &mut evaluator::Evaluator) -> Signal {
     if args.len() != 1 {
-        return Object::new_error(format!(
-            "wrong number of arguments. got={}, want=1",
-            args.len()
+        return Signal::Throw(Object::new_error(
+            ARGUMENT_ERROR,
+            format!("wrong number of arguments. got={}, want=1", args.len()),
         ));
     }
     match &*args[0] {
@@ -74,111 +86,180 @@ fn strainer_import(args: Vec<Rc<Object>>, eval: &mut evaluator::Evaluator) -> Rc
             let input = match data {
                 Ok(content) => content,
                 Err(error) => {
-                    return object::Object::new_error(format!(
-                        "Could not open or find file: {}",
-                        error
+                    return Signal::Throw(object::Object::new_error(
+                        IMPORT_ERROR,
+                        format!("Could not open or find file: {}", error),
                     ));
                 }
             };
 
-            let l = lexer::Lexer::new(&input);
-            let mut p = parser::Parser::new(l);
-            let program = p.parse_program();
-
-            if p.errors.len() != 0 {
-                let mut s = format!("parser errors:\r\n");
-                for err in p.errors {
-                    s += &format!("\t{}\r\n", err);
-                }
-                return object::Object::new_error(s);
-            }
-            if program.need_next() {
-                let mut s = format!("parser errors:\r\n");
-                for err in p.errors {
-                    s += &format!("\t{}\r\n", err);
+            let l = lexer::Lexer::new_with_file(&input, s);
+            let program = match parser::Parser::new(l).parse() {
+                parser::ParseOutcome::Complete(program) => program,
+                parser::ParseOutcome::Incomplete(errors)
+                | parser::ParseOutcome::Invalid(errors) => {
+                    // Rendered without colour: the text ends up in an error value.
+                    let renderer = diagnostics::Renderer::new(false);
+                    let mut message = format!("could not import {}:\n", s);
+                    for err in &errors {
+                        let diagnostic = diagnostics::Diagnostic::from_parse_error(err);
+                        message += &renderer.render(&diagnostic, Some(s), &input);
+                    }
+                    return Signal::Throw(object::Object::new_error(
+                        IMPORT_ERROR,
+                        message.trim_end().to_string(),
+                    ));
                 }
-                return object::Object::new_error(s);
-            }
+            };
 
-            match eval.eval_program(program) {
-                Some(evaluated) => {
-                    return evaluated;
-                }
-                None => return object::Object::new_error(format!("cannot evaluate error!\r\n")),
-            }
+            // The same file can be named by different paths.
+            let file = match canonicalize(s) {
+                Ok(path) => path.display().to_string(),
+                Err(_) => s.clone(),
+            };
+            return eval.eval_import(file, program);
         }
-        o => Object::new_error(format!("argument to `len` not supported, got {}", o)),
+        o => Signal::Throw(Object::new_error(
+            TYPE_ERROR,
+            format!("argument to `import` not supported, got {}", o),
+        )),
     }
 }
 
-fn strainer_len(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Rc<Object> {
+fn strainer_len(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Signal {
     if args.len() != 1 {
-        return Object::new_error(format!(
-            "wrong number of arguments. got={}, want=1",
-            args.len()
+        return Signal::Throw(Object::new_error(
+            ARGUMENT_ERROR,
+            format!("wrong number of arguments. got={}, want=1", args.len()),
         ));
     }
     match &*args[0] {
-        Object::String(s) => Rc::new(Object::Integer(s.len() as i64)),
-        Object::Array(s) => Rc::new(Object::Integer(s.len() as i64)),
-        o => Object::new_error(format!("argument to `len` not supported, got {}", o)),
+        Object::String(s) => Signal::Value(Rc::new(Object::Integer(s.len() as i64))),
+        Object::Array(s) => Signal::Value(Rc::new(Object::Integer(s.len() as i64))),
+        o => Signal::Throw(Object::new_error(
+            TYPE_ERROR,
+            format!("argument to `len` not supported, got {}", o),
+        )),
     }
 }
 
-fn strainer_first(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Rc<Object> {
+fn strainer_first(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Signal {
+    if args.len() != 1 {
+        return Signal::Throw(Object::new_error(
+            ARGUMENT_ERROR,
+            format!("wrong number of arguments. got={}, want=1", args.len()),
+        ));
+    }
     match &*args[0] {
         Object::Array(o) => {
             if let Some(ao) = o.first() {
-                Rc::clone(ao)
+                Signal::Value(Rc::clone(ao))
             } else {
-                Rc::new(NULL)
+                Signal::Value(Rc::new(NULL))
             }
         }
-        o => Object::new_error(format!("argument to `first` must be array. got {}", o)),
+        o => Signal::Throw(Object::new_error(
+            TYPE_ERROR,
+            format!("argument to `first` must be array. got {}", o),
+        )),
     }
 }
 
-fn strainer_last(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Rc<Object> {
+fn strainer_last(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Signal {
+    if args.len() != 1 {
+        return Signal::Throw(Object::new_error(
+            ARGUMENT_ERROR,
+            format!("wrong number of arguments. got={}, want=1", args.len()),
+        ));
+    }
     match &*args[0] {
         Object::Array(o) => {
             if let Some(ao) = o.last() {
-                ao.clone()
+                Signal::Value(ao.clone())
             } else {
-                Rc::new(NULL)
+                Signal::Value(Rc::new(NULL))
             }
         }
-        o => Object::new_error(format!("argument to `last` must be array. got {}", o)),
+        o => Signal::Throw(Object::new_error(
+            TYPE_ERROR,
+            format!("argument to `last` must be array. got {}", o),
+        )),
     }
 }
 
-fn strainer_rest(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Rc<Object> {
+fn strainer_rest(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Signal {
+    if args.len() != 1 {
+        return Signal::Throw(Object::new_error(
+            ARGUMENT_ERROR,
+            format!("wrong number of arguments. got={}, want=1", args.len()),
+        ));
+    }
     match &*args[0] {
         Object::Array(o) => {
             if o.len() > 0 {
-                Rc::new(Object::Array(o[1..].to_vec()))
+                Signal::Value(Rc::new(Object::Array(o[1..].to_vec())))
             } else {
-                Rc::new(NULL)
+                Signal::Value(Rc::new(NULL))
             }
         }
-        o => Object::new_error(format!("argument to `rest` must be array. got {}", o)),
+        o => Signal::Throw(Object::new_error(
+            TYPE_ERROR,
+            format!("argument to `rest` must be array. got {}", o),
+        )),
     }
 }
 
-fn strainer_push(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Rc<Object> {
+fn strainer_push(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Signal {
+    if args.len() != 2 {
+        return Signal::Throw(Object::new_error(
+            ARGUMENT_ERROR,
+            format!("wrong number of arguments. got={}, want=2", args.len()),
+        ));
+    }
     match &*args[0] {
         Object::Array(o) => {
             let mut arr = o.clone();
             arr.push(args[1].clone());
-            Rc::new(Object::Array(arr))
+            Signal::Value(Rc::new(Object::Array(arr)))
         }
-        o => Object::new_error(format!("argument to `push` must be array. got {}", o)),
+        o => Signal::Throw(Object::new_error(
+            TYPE_ERROR,
+            format!("argument to `push` must be array. got {}", o),
+        )),
     }
 }
 
-fn strainer_puts(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Rc<Object> {
+fn strainer_puts(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Signal {
     for arg in args {
-        print!("{}\r\n", arg.string());
+        match &*arg {
+            Object::String(s) => print!("{}\r\n", s),
+            o => print!("{}\r\n", o.string()),
+        }
     }
 
-    return Rc::new(NULL);
+    return Signal::Value(Rc::new(NULL));
+}
+
+fn strainer_range(args: Vec<Rc<Object>>, _: &mut evaluator::Evaluator) -> Signal {
+    let (start, end) = match args.as_slice() {
+        [end] => (&Object::Integer(0), &**end),
+        [start, end] => (&**start, &**end),
+        _ => {
+            return Signal::Throw(Object::new_error(
+                ARGUMENT_ERROR,
+                format!("wrong number of arguments. got={}, want=1 or 2", args.len()),
+            ))
+        }
+    };
+
+    match (start, end) {
+        (Object::Integer(start), Object::Integer(end)) => Signal::Value(Rc::new(Object::Range {
+            start: *start,
+            end: *end,
+        })),
+        (Object::Integer(_), o) | (o, _) => Signal::Throw(Object::new_error(
+            TYPE_ERROR,
+            format!("argument to `range` must be INTEGER, got {}", o),
+        )),
+    }
 }
@@ -0,0 +1,344 @@
+use super::{object, parser, token};
+use std::fs::read_to_string;
+use termion::{color, style};
+
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<token::Span>,
+    pub help: Option<String>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn from_parse_error(err: &parser::ParseError) -> Diagnostic {
+        Diagnostic {
+            message: err.message(),
+            span: Some(err.span.clone()),
+            help: err.help(),
+            notes: Vec::new(),
+        }
+    }
+
+    // Returns None unless `obj` is an error value.
+    pub fn from_object(obj: &object::Object) -> Option<Diagnostic> {
+        match obj {
+            object::Object::Error {
+                message,
+                span,
+                trace,
+                ..
+            } => {
+                // Each call is shown with where it had got to when the error
+                // left it: the error itself for the innermost one, the call
+                // it was making for the others.
+                // A run of identical calls, as left by deep recursion, is
+                // shown once with a count.
+                let mut location = span.clone();
+                let mut notes: Vec<String> = Vec::new();
+                let mut repeats = 0;
+                for (function, call_site) in trace {
+                    let note = match &location {
+                        Some(location) => {
+                            format!("in {} ({}) called from {}", function, location, call_site)
+                        }
+                        None => format!("in {} called from {}", function, call_site),
+                    };
+                    location = Some(call_site.clone());
+                    if notes.last() == Some(&note) {
+                        repeats += 1;
+                        continue;
+                    }
+                    if repeats > 0 {
+                        notes.push(Diagnostic::repeated(repeats));
+                        repeats = 0;
+                    }
+                    notes.push(note);
+                }
+                if repeats > 0 {
+                    notes.push(Diagnostic::repeated(repeats));
+                }
+                Some(Diagnostic {
+                    message: message.clone(),
+                    span: span.clone(),
+                    help: None,
+                    notes,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn repeated(repeats: usize) -> String {
+        match repeats {
+            1 => "the call above repeated 1 more time".to_string(),
+            _ => format!("the call above repeated {} more times", repeats),
+        }
+    }
+}
+
+// Renders diagnostics the way compilers do:
+//
+//   error: expected `)`, found `let`
+//    --> main.str:4:1
+//     |
+//   4 | let z = 3;
+//     | ^^^
+//     = help: separate items with `,` and close the list with `)`
+pub struct Renderer {
+    pub colour: bool,
+}
+
+impl Renderer {
+    pub fn new(colour: bool) -> Renderer {
+        Renderer { colour }
+    }
+
+    // `source` is the text that was parsed as `file`. Spans pointing into
+    // another file are looked up on disk.
+    pub fn render(&self, diagnostic: &Diagnostic, file: Option<&str>, source: &str) -> String {
+        let mut s = format!(
+            "{}: {}\n",
+            self.paint("error", &color::Red),
+            self.bold(&diagnostic.message)
+        );
+
+        if let Some(span) = &diagnostic.span {
+            let other_source;
+            let source = if span.file.as_deref() == file {
+                Some(source)
+            } else {
+                other_source = span.file.as_deref().and_then(|f| read_to_string(f).ok());
+                other_source.as_deref()
+            };
+            let snippet = source.and_then(|source| Renderer::snippet(span, source));
+
+            let line = span.start.line.to_string();
+            let pad = " ".repeat(line.len());
+            s += &format!("{}{} {}\n", pad, self.paint("-->", &color::Blue), span);
+            if let Some((text, indent, width)) = snippet {
+                let gutter = self.paint("|", &color::Blue);
+                s += &format!("{} {}\n", pad, gutter);
+                s += &format!("{} {} {}\n", self.paint(&line, &color::Blue), gutter, text);
+                s += &format!(
+                    "{} {} {}{}\n",
+                    pad,
+                    gutter,
+                    indent,
+                    self.paint(&"^".repeat(width), &color::Red)
+                );
+            }
+            if let Some(help) = &diagnostic.help {
+                s += &format!("{} {} help: {}\n", pad, self.paint("=", &color::Blue), help);
+            }
+            for note in &diagnostic.notes {
+                s += &format!("{} {} note: {}\n", pad, self.paint("=", &color::Blue), note);
+            }
+        } else {
+            if let Some(help) = &diagnostic.help {
+                s += &format!("{} help: {}\n", self.paint("=", &color::Blue), help);
+            }
+            for note in &diagnostic.notes {
+                s += &format!("{} note: {}\n", self.paint("=", &color::Blue), note);
+            }
+        }
+
+        s
+    }
+
+    // The line `span` starts on, the whitespace leading up to the span and
+    // the number of characters to underline. None when `source` does not
+    // match the span, e.g. it was edited since.
+    fn snippet(span: &token::Span, source: &str) -> Option<(String, String, usize)> {
+        let start = span.start.offset;
+        if !source.is_char_boundary(start) {
+            return None;
+        }
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let before = &source[line_start..start];
+        if source[..start].matches('\n').count() + 1 != span.start.line
+            || before.chars().count() + 1 != span.start.column
+        {
+            return None;
+        }
+
+        let end = span.end.offset.max(start).min(line_end);
+        let width = match source.get(start..end) {
+            Some(text) => text.trim_end_matches('\r').chars().count().max(1),
+            None => 1,
+        };
+        let indent = before
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let text = source[line_start..line_end]
+            .trim_end_matches('\r')
+            .to_string();
+
+        Some((text, indent, width))
+    }
+
+    fn paint(&self, text: &str, colour: &dyn color::Color) -> String {
+        if !self.colour {
+            return text.to_string();
+        }
+        format!(
+            "{}{}{}{}",
+            style::Bold,
+            color::Fg(colour),
+            text,
+            style::Reset
+        )
+    }
+
+    fn bold(&self, text: &str) -> String {
+        if !self.colour {
+            return text.to_string();
+        }
+        format!("{}{}{}", style::Bold, text, style::Reset)
+    }
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use super::*;
+    use crate::{evaluator, lexer};
+
+    fn parse_errors(input: &str) -> Vec<parser::ParseError> {
+        let l = lexer::Lexer::new_with_file(input, "main.str");
+        match parser::Parser::new(l).parse() {
+            parser::ParseOutcome::Complete(_) => panic!("no parse errors in {}", input),
+            parser::ParseOutcome::Incomplete(errors) | parser::ParseOutcome::Invalid(errors) => {
+                errors
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_parse_errors() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                (
+                    "let x = 1;\nadd(x y);",
+                    "error: expected `)`, found identifier\n --> main.str:2:7\n  |\n2 | add(x y);\n  |       ^\n  = help: separate items with `,` and close the list with `)`\n",
+                ),
+                (
+                    "let = 5;",
+                    "error: expected identifier, found `=`\n --> main.str:1:5\n  |\n1 | let = 5;\n  |     ^\n",
+                ),
+                (
+                    "if (x) {\n\tlet y = ]\n}",
+                    "error: expected an expression, found `]`\n --> main.str:2:10\n  |\n2 | \tlet y = ]\n  | \t        ^\n  = help: an expression is missing here\n",
+                ),
+                (
+                    "let 名前 = 99999999999999999999;",
+                    "error: could not parse 99999999999999999999 as integer\n --> main.str:1:10\n  |\n1 | let 名前 = 99999999999999999999;\n  |          ^^^^^^^^^^^^^^^^^^^^\n  = help: integers range from -9223372036854775808 to 9223372036854775807\n",
+                ),
+                (
+                    "let f = fn(x) {\n  x",
+                    "error: expected `}`, found end of input\n --> main.str:2:4\n  |\n2 |   x\n  |    ^\n  = help: add the missing `}`\n",
+                ),
+            ]
+        );
+
+        let renderer = Renderer::new(false);
+        for (input, expected) in tests {
+            let errors = parse_errors(input);
+            let rendered = renderer.render(
+                &Diagnostic::from_parse_error(&errors[0]),
+                Some("main.str"),
+                input,
+            );
+            assert_eq!(rendered, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_render_runtime_error() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                (
+                    "let x = 1;\nx + y;",
+                    "error: identifier not found: y\n --> main.str:2:5\n  |\n2 | x + y;\n  |     ^\n",
+                ),
+                (
+                    "let f = fn(x) {\n  x + y;\n};\nf(1);",
+                    "error: identifier not found: y\n --> main.str:2:7\n  |\n2 |   x + y;\n  |       ^\n  = note: in f (main.str:2:7) called from main.str:4:1\n",
+                ),
+                (
+                    "let inner = fn(x) {\n  x / 0\n};\nfn outer(y) { inner(y) }\nouter(1);",
+                    "error: division by zero\n --> main.str:2:3\n  |\n2 |   x / 0\n  |   ^^^^^\n  = note: in inner (main.str:2:3) called from main.str:4:15\n  = note: in outer (main.str:4:15) called from main.str:5:1\n",
+                ),
+                (
+                    "[fn() { len(1) }][0]()",
+                    "error: argument to `len` not supported, got INTEGER\n --> main.str:1:9\n  |\n1 | [fn() { len(1) }][0]()\n  |         ^^^^^^\n  = note: in anonymous function (main.str:1:9) called from main.str:1:1\n",
+                ),
+                (
+                    "fn f(n) { if (n == 0) { nope } f(n - 1) }\nf(3)",
+                    "error: identifier not found: nope\n --> main.str:1:25\n  |\n1 | fn f(n) { if (n == 0) { nope } f(n - 1) }\n  |                         ^^^^\n  = note: in f (main.str:1:25) called from main.str:1:32\n  = note: in f (main.str:1:32) called from main.str:1:32\n  = note: the call above repeated 1 more time\n  = note: in f (main.str:1:32) called from main.str:2:1\n",
+                ),
+                (
+                    "fn f(n) { if (n == 0) { nope } f(n - 1) }\nf(4)",
+                    "error: identifier not found: nope\n --> main.str:1:25\n  |\n1 | fn f(n) { if (n == 0) { nope } f(n - 1) }\n  |                         ^^^^\n  = note: in f (main.str:1:25) called from main.str:1:32\n  = note: in f (main.str:1:32) called from main.str:1:32\n  = note: the call above repeated 2 more times\n  = note: in f (main.str:1:32) called from main.str:2:1\n",
+                ),
+            ]
+        );
+
+        for (input, expected) in tests {
+            let l = lexer::Lexer::new_with_file(input, "main.str");
+            let program = match parser::Parser::new(l).parse() {
+                parser::ParseOutcome::Complete(program) => program,
+                _ => panic!("parse errors in {}", input),
+            };
+            let error = match evaluator::Evaluator::new().eval_program(program) {
+                evaluator::Signal::Throw(error) => error,
+                _ => panic!("no error from {}", input),
+            };
+
+            let diagnostic = Diagnostic::from_object(&error).unwrap();
+            let rendered = Renderer::new(false).render(&diagnostic, Some("main.str"), input);
+            assert_eq!(rendered, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_render_without_snippet() {
+        let renderer = Renderer::new(false);
+        let diagnostic = Diagnostic {
+            message: "something went wrong".to_string(),
+            span: None,
+            help: Some("try again".to_string()),
+            notes: vec!["in f called from 1:1".to_string()],
+        };
+        assert_eq!(
+            renderer.render(&diagnostic, None, ""),
+            "error: something went wrong\n= help: try again\n= note: in f called from 1:1\n"
+        );
+
+        // The span points past the source it is rendered against.
+        let errors = parse_errors("\n\n\nlet = 1;");
+        let rendered = renderer.render(
+            &Diagnostic::from_parse_error(&errors[0]),
+            Some("main.str"),
+            "let = 1;",
+        );
+        assert_eq!(
+            rendered,
+            "error: expected identifier, found `=`\n --> main.str:4:5\n"
+        );
+    }
+
+    #[test]
+    fn test_render_colour() {
+        let errors = parse_errors("let = 5;");
+        let diagnostic = Diagnostic::from_parse_error(&errors[0]);
+
+        let plain = Renderer::new(false).render(&diagnostic, Some("main.str"), "let = 5;");
+        let coloured = Renderer::new(true).render(&diagnostic, Some("main.str"), "let = 5;");
+        assert!(!plain.contains('\x1b'));
+        assert!(coloured.contains(&format!("{}", color::Fg(color::Red))));
+        assert_ne!(plain, coloured);
+    }
+}
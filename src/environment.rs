@@ -15,7 +15,6 @@ impl Environment {
         s.insert("null".to_string(), Rc::new(object::NULL));
         s.insert("true".to_string(), Rc::new(object::TRUE));
         s.insert("false".to_string(), Rc::new(object::FALSE));
-        s.insert("exit".to_string(), Rc::new(object::EXIT));
         return Environment {
             store: s,
             outer: None,
@@ -41,7 +40,16 @@ impl Environment {
     pub fn set(&mut self, name: String, val: Rc<object::Object>) {
         self.store.insert(name, val);
     }
-    pub fn contains_key(&mut self, name: &str) -> bool {
-        return self.store.contains_key(name);
+    // Updates the nearest existing binding of `name`, looking through the
+    // enclosing environments. Returns false when `name` is not bound anywhere.
+    pub fn assign(&mut self, name: &str, val: Rc<object::Object>) -> bool {
+        if let Some(slot) = self.store.get_mut(name) {
+            *slot = val;
+            return true;
+        }
+        match &self.outer {
+            Some(out_env) => out_env.borrow_mut().assign(name, val),
+            None => false,
+        }
     }
 }
@@ -1,12 +1,57 @@
-use super::{ast, builtin, environment, object};
+use super::{ast, builtin, environment, object, token};
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::rc::Rc;
 
+// How evaluating a piece of the program ended. Anything but `Value` unwinds
+// through the enclosing constructs until one of them handles it: a function
+// call takes a `Return`, a loop a `Break` or `Continue`, a `try` a `Throw`.
+pub enum Signal {
+    Value(Rc<object::Object>),
+    Return(Rc<object::Object>),
+    Break,
+    Continue,
+    Exit(i32),
+    // Carries an `Object::Error`.
+    Throw(Rc<object::Object>),
+}
+
+// The value of a `Signal::Value`. Other signals are returned from the
+// enclosing function.
+macro_rules! value {
+    ($signal:expr) => {
+        match $signal {
+            Signal::Value(value) => value,
+            signal => return signal,
+        }
+    };
+}
+
+// Calls nested deeper than this throw a `RecursionError` instead of
+// overflowing the native stack.
+const MAX_CALL_DEPTH: usize = 2000;
+
+// The native stack to run the evaluator on. Every call takes several nested
+// Rust frames, so the default stack runs out long before `MAX_CALL_DEPTH`.
+pub const STACK_SIZE: usize = 512 * 1024 * 1024;
+
+// A call to a function that has not returned yet.
+#[derive(PartialEq)]
+struct Frame {
+    function: String,
+    call_site: token::Span,
+    // Where evaluation continues once the call ends.
+    caller_env: Rc<RefCell<environment::Environment>>,
+}
+
 #[derive(PartialEq)]
 pub struct Evaluator {
     env: Rc<RefCell<environment::Environment>>,
     builtin: HashMap<String, Rc<object::Object>>,
+    frames: Vec<Frame>,
+    // Files whose import has not finished yet, outermost first.
+    imports: Vec<String>,
 }
 
 impl Evaluator {
@@ -14,155 +59,146 @@ impl Evaluator {
         return Evaluator {
             env: Rc::new(RefCell::new(environment::Environment::new())),
             builtin: builtin::new_builtins(),
+            frames: Vec::new(),
+            imports: Vec::new(),
         };
     }
 
-    pub fn eval_program(&mut self, program: ast::Program) -> Option<Rc<object::Object>> {
+    // A `return` at the top level ends the program with its value.
+    pub fn eval_program(&mut self, program: ast::Program) -> Signal {
         match self.eval_block_statement(program.statements) {
-            Some(r) => match &*r {
-                object::Object::Return(value) => return Some(Rc::clone(value)),
-                _ => return Some(r),
-            },
-            None => return None,
+            Signal::Return(value) => return Signal::Value(value),
+            signal => return signal,
         }
     }
 
-    fn eval_block_statement(
-        &mut self,
-        statements: Vec<ast::Statement>,
-    ) -> Option<Rc<object::Object>> {
-        let mut result = None;
-        for stmt in statements {
-            result = self.eval_statement(stmt);
-            if let Some(r) = result {
-                match &*r {
-                    object::Object::Return(_) => return Some(Rc::clone(&r)),
-                    object::Object::Exit => return Some(Rc::new(object::EXIT)),
-                    object::Object::Error(_) => return Some(Rc::clone(&r)),
-                    _ => result = Some(r),
-                }
+    fn eval_block_statement(&mut self, statements: Vec<ast::Statement>) -> Signal {
+        // Function declarations can be called from anywhere in their block,
+        // including from each other.
+        for stmt in &statements {
+            if let ast::Statement::FunctionDeclaration {
+                name,
+                parameters,
+                body,
+                ..
+            } = stmt
+            {
+                let function = Rc::new(object::Object::Function {
+                    name: Some(name.clone()),
+                    parameters: parameters.clone(),
+                    env: Rc::clone(&self.env),
+                    body: body.clone(),
+                });
+                self.env.borrow_mut().set(name.clone(), function);
             }
         }
-        return result;
+
+        let mut result = Rc::new(object::NULL);
+        for stmt in statements {
+            result = value!(self.eval_statement(stmt));
+        }
+        return Signal::Value(result);
     }
-    fn eval_statement(&mut self, stmt: ast::Statement) -> Option<Rc<object::Object>> {
+    fn eval_statement(&mut self, stmt: ast::Statement) -> Signal {
         match stmt {
-            ast::Statement::LetStatement { name, value } => match self.eval_expression(value) {
-                Some(val) => {
-                    if !Evaluator::is_error(&val) {
-                        self.env.borrow_mut().set(name.to_string(), Rc::clone(&val));
-                    }
-                    return Some(Rc::clone(&val));
+            ast::Statement::LetStatement { name, value, .. } => {
+                let val = value!(self.eval_expression(value));
+                // Nothing is bound unless the whole pattern matches.
+                let mut bindings = Vec::new();
+                if let Err(err) = Evaluator::destructure(&name, &val, &mut bindings) {
+                    return Signal::Throw(err);
                 }
-                None => return None,
-            },
-            ast::Statement::ReturnStatement { return_value } => {
-                match self.eval_expression(return_value) {
-                    Some(value) => {
-                        if Evaluator::is_error(&value) {
-                            return Some(value);
-                        }
-                        return Some(Rc::new(object::Object::Return(Rc::clone(&value))));
-                    }
-                    None => return None,
+                for (name, value) in bindings {
+                    self.env.borrow_mut().set(name, value);
                 }
+                return Signal::Value(val);
+            }
+            ast::Statement::ReturnStatement { return_value, .. } => {
+                return Signal::Return(value!(self.eval_expression(return_value)));
             }
-            ast::Statement::ExpressionStatement { expression } => {
+            ast::Statement::ExpressionStatement { expression, .. } => {
                 return self.eval_expression(expression)
             }
-            ast::Statement::BlockStatement { statements } => {
+            ast::Statement::BlockStatement { statements, .. } => {
                 return self.eval_block_statement(statements)
             }
+            // Bound when its block was entered.
+            ast::Statement::FunctionDeclaration { .. } => Signal::Value(Rc::new(object::NULL)),
+            ast::Statement::BreakStatement { .. } => Signal::Break,
+            ast::Statement::ContinueStatement { .. } => Signal::Continue,
+            ast::Statement::ThrowStatement { value, span } => {
+                let value = value!(self.eval_expression(value));
+                Signal::Throw(Evaluator::thrown_error(&value, span))
+            }
         }
     }
-    fn eval_expression(&mut self, exp: ast::Expression) -> Option<Rc<object::Object>> {
+    fn eval_expression(&mut self, exp: ast::Expression) -> Signal {
+        let span = exp.span().clone();
+        match self.eval_expression_node(exp) {
+            Signal::Throw(err) => Signal::Throw(Evaluator::locate_error(err, span)),
+            signal => signal,
+        }
+    }
+
+    fn eval_expression_node(&mut self, exp: ast::Expression) -> Signal {
         match exp {
-            ast::Expression::Identifier { value } => return self.eval_identifier(value),
-            ast::Expression::IntegerLiteral { value } => {
-                return Some(Rc::new(object::Object::Integer(value)))
-            }
-            ast::Expression::StringLiteral { value } => {
-                return Some(Rc::new(object::Object::String(value)))
-            }
-            ast::Expression::PrefixExpression { operator, right } => {
-                match self.eval_expression(*right) {
-                    Some(right_evaluated) => {
-                        if Evaluator::is_error(&right_evaluated) {
-                            return Some(right_evaluated);
-                        }
-                        return Evaluator::eval_prefix_expression(operator, right_evaluated);
-                    }
-                    None => return None,
-                }
+            ast::Expression::Identifier { value, .. } => return self.eval_identifier(value),
+            ast::Expression::IntegerLiteral { value, .. } => {
+                return Signal::Value(Rc::new(object::Object::Integer(value)))
+            }
+            ast::Expression::FloatLiteral { value, .. } => {
+                Signal::Value(Rc::new(object::Object::Float(value)))
+            }
+            ast::Expression::StringLiteral { value, .. } => {
+                return Signal::Value(Rc::new(object::Object::String(value)))
+            }
+            ast::Expression::PrefixExpression {
+                operator, right, ..
+            } => {
+                let right_evaluated = value!(self.eval_expression(*right));
+                return Evaluator::eval_prefix_expression(operator, right_evaluated);
             }
             ast::Expression::InfixExpression {
                 left,
                 operator,
                 right,
-            } => match self.eval_expression(*right) {
-                Some(right_evaluated) => {
-                    if Evaluator::is_error(&right_evaluated) {
-                        return Some(right_evaluated);
-                    }
-                    match self.eval_expression(*left) {
-                        Some(left_evaluated) => {
-                            if Evaluator::is_error(&left_evaluated) {
-                                return Some(left_evaluated);
-                            }
-                            return self.eval_infix_expression(
-                                operator.to_string(),
-                                left_evaluated,
-                                right_evaluated,
-                            );
-                        }
-                        None => return None,
-                    }
-                }
-                None => return None,
-            },
-            ast::Expression::AssignExpression { left, right } => {
-                match self.eval_expression(*right) {
-                    Some(right_evaluated) => {
-                        if Evaluator::is_error(&right_evaluated) {
-                            return Some(right_evaluated);
-                        }
-                        match *left {
-                            ast::Expression::Identifier { value } => {
-                                if !self.env.borrow_mut().contains_key(&value) {
-                                    return Some(object::Object::new_error(format!(
-                                        "{} is not defined before.",
-                                        &value
-                                    )));
-                                }
-                                self.env
-                                    .borrow_mut()
-                                    .set(value, Rc::clone(&right_evaluated));
-                                return Some(right_evaluated);
-                            }
-                            _ => return None,
-                        }
-                    }
-                    None => return None,
-                }
+                ..
+            } if operator == "&&" || operator == "||" => {
+                self.eval_logical_expression(operator, *left, *right)
             }
-            ast::Expression::Boolean { value } => return Some(Evaluator::eval_boolean(value)),
-            ast::Expression::ArrayLiteral { elements } => {
-                let elms = self.eval_expressions(elements);
-                if elms.len() == 1 && Evaluator::is_error(&elms[0]) {
-                    return Some(elms[0].clone());
-                }
-                return Some(Rc::new(object::Object::Array(elms)));
+            ast::Expression::InfixExpression {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                let left_evaluated = value!(self.eval_expression(*left));
+                let right_evaluated = value!(self.eval_expression(*right));
+                return self.eval_infix_expression(
+                    operator.to_string(),
+                    left_evaluated,
+                    right_evaluated,
+                );
             }
-            ast::Expression::IndexExpression { left, index } => {
-                let left = self.eval_expression(*left)?;
-                let index = self.eval_expression(*index)?;
-
-                if Evaluator::is_error(&left) {
-                    return Some(left);
-                }
-                if Evaluator::is_error(&index) {
-                    return Some(index);
-                }
+            ast::Expression::AssignExpression {
+                left,
+                operator,
+                right,
+                ..
+            } => return self.eval_assign_expression(*left, operator, *right),
+            ast::Expression::Boolean { value, .. } => {
+                return Signal::Value(Evaluator::eval_boolean(value))
+            }
+            ast::Expression::ArrayLiteral { elements, .. } => {
+                let elms = match self.eval_expressions(elements) {
+                    Ok(elms) => elms,
+                    Err(signal) => return signal,
+                };
+                return Signal::Value(Rc::new(object::Object::Array(elms)));
+            }
+            ast::Expression::IndexExpression { left, index, .. } => {
+                let left = value!(self.eval_expression(*left));
+                let index = value!(self.eval_expression(*index));
 
                 return Evaluator::eval_index_expression(left, index);
             }
@@ -170,158 +206,740 @@ impl Evaluator {
                 condition,
                 consequence,
                 alternative,
+                ..
             } => return self.eval_if_expression(condition, consequence, alternative),
             ast::Expression::WhileExpression {
                 condition,
                 consequence,
+                ..
             } => return self.eval_while_expression(condition, consequence),
-            ast::Expression::FunctionLiteral { parameters, body } => {
-                return Some(Rc::new(object::Object::Function {
+            ast::Expression::ForExpression {
+                variables,
+                iterable,
+                body,
+                ..
+            } => self.eval_for_expression(variables, *iterable, *body),
+            ast::Expression::FunctionLiteral {
+                parameters, body, ..
+            } => {
+                return Signal::Value(Rc::new(object::Object::Function {
+                    name: None,
                     parameters,
                     env: Rc::clone(&self.env),
-                    body: *body,
+                    body,
                 }))
             }
             ast::Expression::CallExpression {
                 function,
                 arguments,
+                named_arguments,
+                span,
             } => {
-                if let Some(func) = self.eval_expression(*function) {
-                    if Evaluator::is_error(&func) {
-                        return Some(func);
-                    }
-                    let args = self.eval_expressions(arguments);
-                    if args.len() == 1 && Evaluator::is_error(&args[0]) {
-                        return Some(args[0].clone());
-                    }
-                    return self.apply_function(func, args);
-                } else {
-                    return None;
+                // How the function shows up in stack traces, unless it was
+                // declared with a name.
+                let callee = match &*function {
+                    ast::Expression::Identifier { value, .. } => value.clone(),
+                    _ => "anonymous function".to_string(),
+                };
+                let func = value!(self.eval_expression(*function));
+                let args = match self.eval_expressions(arguments) {
+                    Ok(args) => args,
+                    Err(signal) => return signal,
+                };
+                let mut named = Vec::new();
+                for (name, value) in named_arguments {
+                    let value = value!(self.eval_expression(value));
+                    named.push((name.to_string(), value));
                 }
+                return self.apply_function(func, args, named, callee, span);
             }
-            ast::Expression::HashLiteral { pairs } => {
+            ast::Expression::HashLiteral { pairs, .. } => {
                 let mut hash = HashMap::new();
 
                 for (key_expr, value_expr) in pairs {
-                    if let Some(key) = self.eval_expression(key_expr) {
-                        if Self::is_error(&key) {
-                            return Some(key);
+                    let key = value!(self.eval_expression(key_expr));
+                    let value = value!(self.eval_expression(value_expr));
+                    hash.insert(key, value);
+                }
+
+                Signal::Value(Rc::new(object::Object::Hash(hash)))
+            }
+            ast::Expression::MatchExpression { subject, arms, .. } => {
+                self.eval_match_expression(*subject, arms)
+            }
+            ast::Expression::TryExpression {
+                body,
+                catch,
+                finally,
+                ..
+            } => self.eval_try_expression(*body, catch, finally),
+            ast::Expression::ArrayPattern { .. } | ast::Expression::HashPattern { .. } => {
+                Signal::Throw(object::Object::new_error(
+                    object::ERROR,
+                    format!("unexpected pattern: {}", exp),
+                ))
+            }
+        }
+    }
+
+    fn eval_match_expression(
+        &mut self,
+        subject: ast::Expression,
+        arms: Vec<ast::MatchArm>,
+    ) -> Signal {
+        let value = value!(self.eval_expression(subject));
+
+        // The first arm whose pattern matches and whose guard holds is taken.
+        // Its bindings live in a scope of their own.
+        let outer_env = Rc::clone(&self.env);
+        for arm in arms {
+            let mut bindings = Vec::new();
+            if !Evaluator::match_pattern(&arm.pattern, &value, &mut bindings) {
+                continue;
+            }
+            let mut arm_env =
+                environment::Environment::new_enclosed_environment(Rc::clone(&outer_env));
+            for (name, value) in bindings {
+                arm_env.set(name, value);
+            }
+            self.env = Rc::new(RefCell::new(arm_env));
+
+            let guard = match arm.guard {
+                Some(guard) => self.eval_expression(guard),
+                None => Signal::Value(Evaluator::eval_boolean(true)),
+            };
+            let evaluated = match guard {
+                Signal::Value(guard) if Evaluator::is_truthy(Rc::clone(&guard)) => {
+                    self.eval_statement(arm.body)
+                }
+                Signal::Value(_) => {
+                    self.env = Rc::clone(&outer_env);
+                    continue;
+                }
+                signal => signal,
+            };
+            self.env = outer_env;
+            return evaluated;
+        }
+
+        Signal::Throw(object::Object::new_error(
+            object::MATCH_ERROR,
+            format!("no match arm matches {}", value.string()),
+        ))
+    }
+
+    fn eval_try_expression(
+        &mut self,
+        body: ast::Statement,
+        catch: Option<(Box<ast::Expression>, Box<ast::Statement>)>,
+        finally: Option<Box<ast::Statement>>,
+    ) -> Signal {
+        let outer_env = Rc::clone(&self.env);
+        let mut result = self.eval_statement(body);
+        self.env = Rc::clone(&outer_env);
+
+        if let (Signal::Throw(error), Some((parameter, block))) = (&result, catch) {
+            let error = Evaluator::error_hash(error);
+            let mut bindings = Vec::new();
+            result = match Evaluator::destructure(&parameter, &error, &mut bindings) {
+                Ok(()) => {
+                    let mut catch_env =
+                        environment::Environment::new_enclosed_environment(Rc::clone(&outer_env));
+                    for (name, value) in bindings {
+                        catch_env.set(name, value);
+                    }
+                    self.env = Rc::new(RefCell::new(catch_env));
+                    self.eval_statement(*block)
+                }
+                Err(err) => Signal::Throw(err),
+            };
+            self.env = Rc::clone(&outer_env);
+        }
+
+        // `finally` always runs. Leaving it early, or failing in it, takes
+        // the place of whatever the rest of the expression produced.
+        if let Some(block) = finally {
+            let finished = self.eval_statement(*block);
+            self.env = outer_env;
+            if !matches!(finished, Signal::Value(_)) {
+                return finished;
+            }
+        }
+        result
+    }
+
+    // The error raised by `throw value`. A hash can give the `message` and
+    // `kind` of the error; anything else becomes its message.
+    fn thrown_error(value: &Rc<object::Object>, span: token::Span) -> Rc<object::Object> {
+        let text = |value: &Rc<object::Object>| match &**value {
+            object::Object::String(s) => s.clone(),
+            _ => value.string(),
+        };
+        let (message, kind) = match &**value {
+            object::Object::Hash(pairs) => {
+                let field = |name: &str| pairs.get(&object::Object::String(name.to_string()));
+                let message = field("message").map(text).unwrap_or_default();
+                let kind = match field("kind").map(|kind| &**kind) {
+                    Some(object::Object::String(kind)) => kind.clone(),
+                    _ => object::ERROR.to_string(),
+                };
+                (message, kind)
+            }
+            _ => (text(value), object::ERROR.to_string()),
+        };
+        Rc::new(object::Object::Error {
+            message,
+            kind,
+            span: Some(span),
+            trace: Vec::new(),
+        })
+    }
+
+    // What a `catch` clause sees: a hash with the `message`, `kind` and
+    // `location` of the error.
+    fn error_hash(error: &Rc<object::Object>) -> Rc<object::Object> {
+        let mut hash = HashMap::new();
+        if let object::Object::Error {
+            message,
+            kind,
+            span,
+            ..
+        } = &**error
+        {
+            let location = match span {
+                Some(span) => object::Object::String(span.to_string()),
+                None => object::NULL,
+            };
+            let mut field = |name: &str, value| {
+                hash.insert(
+                    Rc::new(object::Object::String(name.to_string())),
+                    Rc::new(value),
+                );
+            };
+            field("message", object::Object::String(message.clone()));
+            field("kind", object::Object::String(kind.clone()));
+            field("location", location);
+        }
+        Rc::new(object::Object::Hash(hash))
+    }
+
+    // Whether `value` has the shape of `pattern`. The variables it binds are
+    // added to `bindings`; `_` matches anything without binding it.
+    fn match_pattern(
+        pattern: &ast::Expression,
+        value: &Rc<object::Object>,
+        bindings: &mut Vec<(String, Rc<object::Object>)>,
+    ) -> bool {
+        match (pattern, &**value) {
+            // `null` is a name bound in every environment, but reads as a literal.
+            (ast::Expression::Identifier { value: name, .. }, _) if name == "null" => {
+                matches!(**value, object::Object::Null)
+            }
+            (ast::Expression::Identifier { value: name, .. }, _) => {
+                if name != "_" {
+                    bindings.push((name.clone(), Rc::clone(value)));
+                }
+                true
+            }
+            (ast::Expression::IntegerLiteral { value: p, .. }, object::Object::Integer(v)) => {
+                p == v
+            }
+            (ast::Expression::FloatLiteral { value: p, .. }, object::Object::Float(v)) => p == v,
+            (ast::Expression::StringLiteral { value: p, .. }, object::Object::String(v)) => p == v,
+            (ast::Expression::Boolean { value: p, .. }, object::Object::Boolean(v)) => p == v,
+            (ast::Expression::PrefixExpression { right, .. }, _) => match (&**right, &**value) {
+                (ast::Expression::IntegerLiteral { value: p, .. }, object::Object::Integer(v)) => {
+                    p.wrapping_neg() == *v
+                }
+                (ast::Expression::FloatLiteral { value: p, .. }, object::Object::Float(v)) => {
+                    -p == *v
+                }
+                _ => false,
+            },
+            (
+                ast::Expression::ArrayPattern { elements, rest, .. },
+                object::Object::Array(items),
+            ) => {
+                let fits = match rest {
+                    Some(_) => items.len() >= elements.len(),
+                    None => items.len() == elements.len(),
+                };
+                fits && elements
+                    .iter()
+                    .zip(items)
+                    .all(|(element, item)| Evaluator::match_pattern(element, item, bindings))
+                    && match rest {
+                        Some(rest) => {
+                            let others = items[elements.len()..].to_vec();
+                            let others = Rc::new(object::Object::Array(others));
+                            Evaluator::match_pattern(rest, &others, bindings)
+                        }
+                        None => true,
+                    }
+            }
+            (ast::Expression::HashPattern { entries, .. }, object::Object::Hash(pairs)) => {
+                entries.iter().all(|(key, pattern)| {
+                    match pairs.get(&object::Object::String(key.to_string())) {
+                        Some(item) => Evaluator::match_pattern(
+                            pattern.as_ref().unwrap_or(key),
+                            item,
+                            bindings,
+                        ),
+                        None => false,
+                    }
+                })
+            }
+            _ => false,
+        }
+    }
+
+    fn destructure(
+        pattern: &ast::Expression,
+        value: &Rc<object::Object>,
+        bindings: &mut Vec<(String, Rc<object::Object>)>,
+    ) -> Result<(), Rc<object::Object>> {
+        let located = |err| Evaluator::locate_error(err, pattern.span().clone());
+
+        match pattern {
+            ast::Expression::Identifier { value: name, .. } => {
+                bindings.push((name.clone(), Rc::clone(value)));
+            }
+            ast::Expression::ArrayPattern { elements, rest, .. } => {
+                let items = match &**value {
+                    object::Object::Array(items) => items,
+                    o => {
+                        return Err(located(object::Object::new_error(
+                            object::MATCH_ERROR,
+                            format!("cannot destructure {} as an array", o),
+                        )))
+                    }
+                };
+                let fits = match rest {
+                    Some(_) => items.len() >= elements.len(),
+                    None => items.len() == elements.len(),
+                };
+                if !fits {
+                    return Err(located(object::Object::new_error(
+                        object::MATCH_ERROR,
+                        format!(
+                            "{} expects {}{} elements, got {}",
+                            pattern,
+                            if rest.is_some() { "at least " } else { "" },
+                            elements.len(),
+                            items.len()
+                        ),
+                    )));
+                }
+
+                for (element, item) in elements.iter().zip(items) {
+                    Evaluator::destructure(element, item, bindings).map_err(located)?;
+                }
+                if let Some(rest) = rest {
+                    let others = Rc::new(object::Object::Array(items[elements.len()..].to_vec()));
+                    Evaluator::destructure(rest, &others, bindings)?;
+                }
+            }
+            ast::Expression::HashPattern { entries, .. } => {
+                let pairs = match &**value {
+                    object::Object::Hash(pairs) => pairs,
+                    o => {
+                        return Err(located(object::Object::new_error(
+                            object::MATCH_ERROR,
+                            format!("cannot destructure {} as a hash", o),
+                        )))
+                    }
+                };
+                for (key, pattern) in entries {
+                    let name = key.to_string();
+                    match pairs.get(&object::Object::String(name.clone())) {
+                        Some(item) => {
+                            Evaluator::destructure(pattern.as_ref().unwrap_or(key), item, bindings)?
                         }
-                        if let Some(value) = self.eval_expression(value_expr) {
-                            if Evaluator::is_error(&value) {
-                                return Some(value);
-                            }
-                            hash.insert(key, value);
+                        None => {
+                            return Err(Evaluator::locate_error(
+                                object::Object::new_error(
+                                    object::MATCH_ERROR,
+                                    format!("key not found: {:?}", name),
+                                ),
+                                key.span().clone(),
+                            ))
                         }
-                    } else {
-                        return None;
                     }
                 }
+            }
+            _ => {
+                return Err(located(object::Object::new_error(
+                    object::ERROR,
+                    format!("invalid binding target: {}", pattern),
+                )))
+            }
+        }
+        Ok(())
+    }
 
-                Some(Rc::new(object::Object::Hash(hash)))
+    fn eval_assign_expression(
+        &mut self,
+        target: ast::Expression,
+        operator: String,
+        value: ast::Expression,
+    ) -> Signal {
+        // `x += y` is `x = x + y` with the target evaluated only once.
+        let operator = operator.strip_suffix('=').filter(|op| !op.is_empty());
+
+        match target {
+            ast::Expression::Identifier { value: name, .. } => {
+                let current = match operator {
+                    Some(_) => Some(value!(self.eval_identifier(name.clone()))),
+                    None => None,
+                };
+                let mut value = value!(self.eval_expression(value));
+                if let (Some(operator), Some(current)) = (operator, current) {
+                    value =
+                        value!(self.eval_infix_expression(operator.to_string(), current, value));
+                }
+                self.assign(name, value)
             }
-            ast::Expression::NeedNext => return None,
+            ast::Expression::IndexExpression { .. } => {
+                // Flatten `a[i][j]` into the variable `a` and the indices `[i, j]`.
+                let mut indices = Vec::new();
+                let mut root = target;
+                while let ast::Expression::IndexExpression { left, index, .. } = root {
+                    indices.push(*index);
+                    root = *left;
+                }
+                indices.reverse();
+
+                let name = match root {
+                    ast::Expression::Identifier { value, .. } => value,
+                    other => {
+                        return Signal::Throw(object::Object::new_error(
+                            object::ERROR,
+                            format!("invalid assignment target: {}", other),
+                        ))
+                    }
+                };
+                let container = value!(self.eval_identifier(name.clone()));
+                let mut keys = Vec::new();
+                for index in indices {
+                    keys.push(value!(self.eval_expression(index)));
+                }
+                let current = match operator {
+                    Some(_) => {
+                        let mut current = Rc::clone(&container);
+                        for key in &keys {
+                            current =
+                                value!(Evaluator::eval_index_expression(current, Rc::clone(key)));
+                        }
+                        Some(current)
+                    }
+                    None => None,
+                };
+                let mut value = value!(self.eval_expression(value));
+                if let (Some(operator), Some(current)) = (operator, current) {
+                    value =
+                        value!(self.eval_infix_expression(operator.to_string(), current, value));
+                }
+
+                match Evaluator::assign_index(&container, &keys, Rc::clone(&value)) {
+                    Ok(updated) => {
+                        value!(self.assign(name, updated));
+                        Signal::Value(value)
+                    }
+                    Err(err) => Signal::Throw(err),
+                }
+            }
+            other => Signal::Throw(object::Object::new_error(
+                object::ERROR,
+                format!("invalid assignment target: {}", other),
+            )),
         }
     }
 
-    fn eval_index_expression(
-        left: Rc<object::Object>,
-        index: Rc<object::Object>,
-    ) -> Option<Rc<object::Object>> {
+    fn assign(&mut self, name: String, value: Rc<object::Object>) -> Signal {
+        if !self.env.borrow_mut().assign(&name, Rc::clone(&value)) {
+            return Signal::Throw(object::Object::new_error(
+                object::NAME_ERROR,
+                format!("{} is not defined before.", name),
+            ));
+        }
+        Signal::Value(value)
+    }
+
+    // Returns a copy of `container` with the element at `keys` replaced by
+    // `value`. Arrays and hashes are values, so nothing else sharing the
+    // original container observes the change.
+    fn assign_index(
+        container: &Rc<object::Object>,
+        keys: &[Rc<object::Object>],
+        value: Rc<object::Object>,
+    ) -> Result<Rc<object::Object>, Rc<object::Object>> {
+        let (key, rest) = match keys.split_first() {
+            Some(split) => split,
+            None => return Ok(value),
+        };
+
+        match &**container {
+            object::Object::Array(elements) => {
+                let i = match **key {
+                    object::Object::Integer(i) => i,
+                    _ => {
+                        return Err(object::Object::new_error(
+                            object::TYPE_ERROR,
+                            format!("array index must be INTEGER, got {}", key),
+                        ))
+                    }
+                };
+                if i < 0 || i >= elements.len() as i64 {
+                    return Err(object::Object::new_error(
+                        object::INDEX_ERROR,
+                        "list index out of range".to_string(),
+                    ));
+                }
+
+                let mut elements = elements.clone();
+                elements[i as usize] = Evaluator::assign_index(&elements[i as usize], rest, value)?;
+                Ok(Rc::new(object::Object::Array(elements)))
+            }
+            object::Object::Hash(pairs) => {
+                match **key {
+                    object::Object::Integer(_)
+                    | object::Object::Boolean(_)
+                    | object::Object::String(_) => {}
+                    _ => {
+                        return Err(object::Object::new_error(
+                            object::TYPE_ERROR,
+                            format!("unusable as hash key: {}", key),
+                        ))
+                    }
+                }
+
+                let element = match (pairs.get(key), rest.is_empty()) {
+                    (_, true) => value,
+                    (Some(inner), false) => Evaluator::assign_index(inner, rest, value)?,
+                    (None, false) => {
+                        return Err(object::Object::new_error(
+                            object::INDEX_ERROR,
+                            format!("key not found: {}", key.string()),
+                        ))
+                    }
+                };
+                let mut pairs = pairs.clone();
+                pairs.insert(Rc::clone(key), element);
+                Ok(Rc::new(object::Object::Hash(pairs)))
+            }
+            o => Err(object::Object::new_error(
+                object::TYPE_ERROR,
+                format!("index assignment not supported: {}", o),
+            )),
+        }
+    }
+
+    fn eval_index_expression(left: Rc<object::Object>, index: Rc<object::Object>) -> Signal {
         if let object::Object::Array(elements) = &*left {
             if let object::Object::Integer(i) = *index {
                 return Evaluator::eval_array_index_expression(elements, i);
             }
         } else if let object::Object::Hash(hash) = &*left {
             if let Some(obj) = hash.get(&index) {
-                return Some(obj.clone());
+                return Signal::Value(obj.clone());
             }
+            return Signal::Throw(object::Object::new_error(
+                object::INDEX_ERROR,
+                format!("key not found: {}", index.string()),
+            ));
         }
 
-        return Some(object::Object::new_error(format!("")));
+        return Signal::Throw(object::Object::new_error(
+            object::TYPE_ERROR,
+            format!("index operator not supported: {}[{}]", left, index),
+        ));
     }
 
-    fn eval_array_index_expression(
-        elements: &Vec<Rc<object::Object>>,
-        i: i64,
-    ) -> Option<Rc<object::Object>> {
+    fn eval_array_index_expression(elements: &Vec<Rc<object::Object>>, i: i64) -> Signal {
         if i < 0 || i > elements.len() as i64 - 1 {
-            return Some(object::Object::new_error(format!(
-                "list index out of range"
-            )));
+            return Signal::Throw(object::Object::new_error(
+                object::INDEX_ERROR,
+                format!("list index out of range"),
+            ));
         }
-        return Some(elements[i as usize].clone());
+        return Signal::Value(elements[i as usize].clone());
     }
 
     fn apply_function(
         &mut self,
         func: Rc<object::Object>,
         args: Vec<Rc<object::Object>>,
-    ) -> Option<Rc<object::Object>> {
+        named: Vec<(String, Rc<object::Object>)>,
+        callee: String,
+        call_site: token::Span,
+    ) -> Signal {
         match &*func {
             object::Object::Function {
+                name,
                 parameters,
                 body,
                 env,
             } => {
-                let current_env = Rc::clone(&self.env);
-                let mut extended_env =
-                    environment::Environment::new_enclosed_environment(Rc::clone(&env));
-                if args.len() != parameters.len() {
-                    return Some(object::Object::new_error(format!(
-                        "wrong number argument. got={}, expected={}",
-                        args.len(),
-                        parameters.len()
+                let extended_env = Rc::new(RefCell::new(
+                    environment::Environment::new_enclosed_environment(Rc::clone(&env)),
+                ));
+                if self.frames.len() >= MAX_CALL_DEPTH {
+                    return Signal::Throw(self.trace_error(object::Object::new_error(
+                        object::RECURSION_ERROR,
+                        "maximum call depth exceeded".to_string(),
                     )));
                 }
-                for (i, p) in parameters.iter().enumerate() {
-                    match p {
-                        ast::Expression::Identifier { value } => {
-                            extended_env.set((&value).to_string(), Rc::clone(&args[i]))
-                        }
-                        _ => return None,
-                    }
+                self.push_frame(name.clone().unwrap_or(callee), call_site, extended_env);
+                let evaluated = match self.bind_arguments(name.as_deref(), parameters, args, named)
+                {
+                    Err(err) => Signal::Throw(self.trace_error(err)),
+                    Ok(()) => match self.eval_statement(*body.clone()) {
+                        Signal::Return(value) => Signal::Value(value),
+                        Signal::Throw(err) => Signal::Throw(self.trace_error(err)),
+                        signal => signal,
+                    },
+                };
+                self.pop_frame();
+                return evaluated;
+            }
+            object::Object::Builtin(_) if !named.is_empty() => {
+                Signal::Throw(object::Object::new_error(
+                    object::ARGUMENT_ERROR,
+                    "builtin functions do not take named arguments".to_string(),
+                ))
+            }
+            object::Object::Builtin(object::BuiltinFunc(_, function)) => function(args, self),
+            o => Signal::Throw(object::Object::new_error(
+                object::TYPE_ERROR,
+                format!("not a function: {}", o),
+            )),
+        }
+    }
+
+    // Runs an imported file in the current scope. A file that ends up
+    // importing itself again would recurse forever, so that is an error.
+    pub fn eval_import(&mut self, file: String, program: ast::Program) -> Signal {
+        if self.imports.contains(&file) {
+            return Signal::Throw(object::Object::new_error(
+                object::IMPORT_ERROR,
+                format!("circular import of {}", file),
+            ));
+        }
+        self.imports.push(file);
+        let evaluated = self.eval_program(program);
+        self.imports.pop();
+        evaluated
+    }
+
+    // Enters the scope `env` of a call. Every push is paired with a
+    // `pop_frame`, whatever signal the call ends with.
+    fn push_frame(
+        &mut self,
+        function: String,
+        call_site: token::Span,
+        env: Rc<RefCell<environment::Environment>>,
+    ) {
+        let caller_env = std::mem::replace(&mut self.env, env);
+        self.frames.push(Frame {
+            function,
+            call_site,
+            caller_env,
+        });
+    }
+
+    fn pop_frame(&mut self) {
+        if let Some(frame) = self.frames.pop() {
+            self.env = frame.caller_env;
+        }
+    }
+
+    // Binds the arguments of a call in the scope of its frame. Missing
+    // arguments take their default, which is evaluated in that scope so it
+    // can refer to the parameters before it.
+    fn bind_arguments(
+        &mut self,
+        name: Option<&str>,
+        parameters: &[ast::Parameter],
+        args: Vec<Rc<object::Object>>,
+        named: Vec<(String, Rc<object::Object>)>,
+    ) -> Result<(), Rc<object::Object>> {
+        let error = |message: String| match name {
+            Some(name) => {
+                object::Object::new_error(object::ARGUMENT_ERROR, format!("{}: {}", name, message))
+            }
+            None => object::Object::new_error(object::ARGUMENT_ERROR, message),
+        };
+
+        let positional = parameters.iter().filter(|p| !p.rest).count();
+        let variadic = parameters.iter().any(|p| p.rest);
+        if args.len() > positional && !variadic {
+            return Err(error(format!(
+                "wrong number argument. got={}, expected={}",
+                args.len(),
+                positional
+            )));
+        }
+
+        let mut args = args.into_iter();
+        let mut values: Vec<Option<Rc<object::Object>>> = parameters
+            .iter()
+            .map(|p| {
+                if p.rest {
+                    Some(Rc::new(object::Object::Array(args.by_ref().collect())))
+                } else {
+                    args.next()
                 }
-                self.env = Rc::new(RefCell::new(extended_env));
-                if let Some(evaluated) = self.eval_statement(body.clone()) {
-                    match &*evaluated {
-                        object::Object::Return(value) => return Some(Rc::clone(value)),
-                        _ => {
-                            return Some(evaluated);
-                        }
-                    }
+            })
+            .collect();
+        for (argument, value) in named {
+            match parameters
+                .iter()
+                .position(|p| p.name == argument && !p.rest)
+            {
+                Some(i) if values[i].is_some() => {
+                    return Err(error(format!("argument {} given more than once", argument)))
                 }
-                self.env = current_env;
-                return None;
+                Some(i) => values[i] = Some(value),
+                None => return Err(error(format!("unknown argument name: {}", argument))),
             }
-            object::Object::Builtin(object::BuiltinFunc(_, function)) => Some(function(args, self)),
-            _ => None,
         }
+
+        for (p, value) in parameters.iter().zip(values) {
+            let value = match (value, &p.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => match self.eval_expression(default.clone()) {
+                    Signal::Value(value) => value,
+                    Signal::Throw(err) => return Err(err),
+                    _ => return Err(error(format!("cannot evaluate default of {}", p.name))),
+                },
+                (None, None) => return Err(error(format!("missing argument: {}", p.name))),
+            };
+            self.env.borrow_mut().set(p.name.clone(), value);
+        }
+        Ok(())
     }
-    fn eval_expressions(&mut self, exps: Vec<ast::Expression>) -> Vec<Rc<object::Object>> {
+    fn eval_expressions(
+        &mut self,
+        exps: Vec<ast::Expression>,
+    ) -> Result<Vec<Rc<object::Object>>, Signal> {
         let mut result = Vec::new();
         for e in exps {
-            if let Some(evaluated) = self.eval_expression(e) {
-                if Evaluator::is_error(&evaluated) {
-                    return vec![evaluated];
-                }
-                result.push(evaluated);
+            match self.eval_expression(e) {
+                Signal::Value(evaluated) => result.push(evaluated),
+                signal => return Err(signal),
             }
         }
-        return result;
+        return Ok(result);
     }
-    fn eval_prefix_expression(
-        operator: String,
-        right: Rc<object::Object>,
-    ) -> Option<Rc<object::Object>> {
+    fn eval_prefix_expression(operator: String, right: Rc<object::Object>) -> Signal {
         match &*operator {
             "!" => return Evaluator::eval_bang_operator_expression(right),
             "-" => return Evaluator::eval_minus_prefix_operator_expression(right),
             _ => {
-                return Some(object::Object::new_error(format!(
-                    "unknown operator: {}{}",
-                    operator, right
-                )))
+                return Signal::Throw(object::Object::new_error(
+                    object::TYPE_ERROR,
+                    format!("unknown operator: {}{}", operator, right),
+                ))
             }
         }
     }
@@ -330,9 +948,11 @@ impl Evaluator {
         operator: String,
         left: Rc<object::Object>,
         right: Rc<object::Object>,
-    ) -> Option<Rc<object::Object>> {
-        let err =
-            object::Object::new_error(format!("type mismatch: {} {} {}", &left, operator, &right));
+    ) -> Signal {
+        let err = object::Object::new_error(
+            object::TYPE_ERROR,
+            format!("type mismatch: {} {} {}", &left, operator, &right),
+        );
         match &*left {
             object::Object::Integer(left_value) => match *right {
                 object::Object::Integer(right_value) => {
@@ -349,7 +969,7 @@ impl Evaluator {
                         right_value,
                     )
                 }
-                _ => return Some(err),
+                _ => return Signal::Throw(err),
             },
             object::Object::Float(left_value) => match *right {
                 object::Object::Integer(right_value) => {
@@ -362,11 +982,11 @@ impl Evaluator {
                 object::Object::Float(right_value) => {
                     return Evaluator::eval_float_infix_expression(
                         operator,
-                        *left_value as f64,
+                        *left_value,
                         right_value,
                     )
                 }
-                _ => return Some(err),
+                _ => return Signal::Throw(err),
             },
             object::Object::String(left_value) => match &*right {
                 object::Object::String(right_value) => {
@@ -376,82 +996,191 @@ impl Evaluator {
                         right_value.clone(),
                     )
                 }
-                _ => return Some(err),
+                _ => return Signal::Throw(err),
             },
             object::Object::Boolean(left_value) => match *right {
                 object::Object::Boolean(right_value) => match &*operator {
-                    "==" => return Some(Evaluator::eval_boolean(*left_value == right_value)),
-                    "!=" => return Some(Evaluator::eval_boolean(*left_value != right_value)),
+                    "==" => {
+                        return Signal::Value(Evaluator::eval_boolean(*left_value == right_value))
+                    }
+                    "!=" => {
+                        return Signal::Value(Evaluator::eval_boolean(*left_value != right_value))
+                    }
+                    "&" => {
+                        return Signal::Value(Evaluator::eval_boolean(*left_value & right_value))
+                    }
+                    "|" => {
+                        return Signal::Value(Evaluator::eval_boolean(*left_value | right_value))
+                    }
+                    "^" => {
+                        return Signal::Value(Evaluator::eval_boolean(*left_value ^ right_value))
+                    }
                     _ => {
-                        return Some(object::Object::new_error(format!(
-                            "unknown operator: {} {} {}",
-                            left, operator, right
-                        )))
+                        return Signal::Throw(object::Object::new_error(
+                            object::TYPE_ERROR,
+                            format!("unknown operator: {} {} {}", left, operator, right),
+                        ))
                     }
                 },
-                _ => return Some(err),
+                _ => return Signal::Throw(err),
             },
             _ => {
-                return Some(object::Object::new_error(format!(
-                    "type mismatch: {} {} {}",
-                    &left, operator, &right
-                )))
+                return Signal::Throw(object::Object::new_error(
+                    object::TYPE_ERROR,
+                    format!("type mismatch: {} {} {}", &left, operator, &right),
+                ))
             }
         }
     }
 
-    fn eval_float(left_value: i64, right_value: i64) -> Rc<object::Object> {
-        let mut under_dot = right_value as f64;
-        while under_dot >= 1.0 {
-            under_dot = under_dot / 10.0;
-        }
-
-        return Rc::new(object::Object::Float(left_value as f64 + under_dot));
-    }
-
     fn eval_integer_infix_expression(
         operator: String,
         left_value: i64,
         right_value: i64,
-    ) -> Option<Rc<object::Object>> {
+    ) -> Signal {
         match &*operator {
-            "+" => return Some(Rc::new(object::Object::Integer(left_value + right_value))),
-            "-" => return Some(Rc::new(object::Object::Integer(left_value - right_value))),
-            "*" => return Some(Rc::new(object::Object::Integer(left_value * right_value))),
-            "/" => return Some(Rc::new(object::Object::Integer(left_value / right_value))),
-            "." => return Some(Evaluator::eval_float(left_value, right_value)),
-            "<" => return Some(Evaluator::eval_boolean(left_value < right_value)),
-            ">" => return Some(Evaluator::eval_boolean(left_value > right_value)),
-            "==" => return Some(Evaluator::eval_boolean(left_value == right_value)),
-            "!=" => return Some(Evaluator::eval_boolean(left_value != right_value)),
-            _ => {
-                return Some(object::Object::new_error(format!(
-                    "unknown operator: {} {} {}",
-                    left_value, operator, right_value
+            "+" => {
+                let value = left_value.checked_add(right_value);
+                return Evaluator::eval_checked_integer(value, operator, left_value, right_value);
+            }
+            "-" => {
+                let value = left_value.checked_sub(right_value);
+                return Evaluator::eval_checked_integer(value, operator, left_value, right_value);
+            }
+            "*" => {
+                let value = left_value.checked_mul(right_value);
+                return Evaluator::eval_checked_integer(value, operator, left_value, right_value);
+            }
+            "/" | "%" if right_value == 0 => {
+                return Signal::Throw(object::Object::new_error(
+                    object::ARITHMETIC_ERROR,
+                    "division by zero".to_string(),
+                ))
+            }
+            "/" => {
+                let value = left_value.checked_div(right_value);
+                return Evaluator::eval_checked_integer(value, operator, left_value, right_value);
+            }
+            // Only `i64::MIN % -1` overflows, and wrapping gives its true result, 0.
+            "%" => {
+                return Signal::Value(Rc::new(object::Object::Integer(
+                    left_value.wrapping_rem(right_value),
                 )))
             }
+            "**" => return Evaluator::eval_integer_power(left_value, right_value),
+            "<" => return Signal::Value(Evaluator::eval_boolean(left_value < right_value)),
+            ">" => return Signal::Value(Evaluator::eval_boolean(left_value > right_value)),
+            "<=" => return Signal::Value(Evaluator::eval_boolean(left_value <= right_value)),
+            ">=" => return Signal::Value(Evaluator::eval_boolean(left_value >= right_value)),
+            "==" => return Signal::Value(Evaluator::eval_boolean(left_value == right_value)),
+            "!=" => return Signal::Value(Evaluator::eval_boolean(left_value != right_value)),
+            "&" => {
+                return Signal::Value(Rc::new(object::Object::Integer(left_value & right_value)))
+            }
+            "|" => {
+                return Signal::Value(Rc::new(object::Object::Integer(left_value | right_value)))
+            }
+            "^" => {
+                return Signal::Value(Rc::new(object::Object::Integer(left_value ^ right_value)))
+            }
+            "<<" | ">>" => {
+                return Evaluator::eval_shift_expression(operator, left_value, right_value)
+            }
+            _ => {
+                return Signal::Throw(object::Object::new_error(
+                    object::TYPE_ERROR,
+                    format!(
+                        "unknown operator: {} {} {}",
+                        left_value, operator, right_value
+                    ),
+                ))
+            }
         }
     }
 
-    fn eval_float_infix_expression(
+    // The result of a checked integer operation, which is None when it
+    // overflowed.
+    fn eval_checked_integer(
+        value: Option<i64>,
         operator: String,
-        left_value: f64,
-        right_value: f64,
-    ) -> Option<Rc<object::Object>> {
+        left_value: i64,
+        right_value: i64,
+    ) -> Signal {
+        match value {
+            Some(value) => Signal::Value(Rc::new(object::Object::Integer(value))),
+            None => Signal::Throw(object::Object::new_error(
+                object::ARITHMETIC_ERROR,
+                format!(
+                    "integer overflow: {} {} {}",
+                    left_value, operator, right_value
+                ),
+            )),
+        }
+    }
+
+    fn eval_integer_power(left_value: i64, right_value: i64) -> Signal {
+        if right_value < 0 {
+            let value = (left_value as f64).powf(right_value as f64);
+            return Signal::Value(Rc::new(object::Object::Float(value)));
+        }
+
+        match u32::try_from(right_value)
+            .ok()
+            .and_then(|exp| left_value.checked_pow(exp))
+        {
+            Some(value) => Signal::Value(Rc::new(object::Object::Integer(value))),
+            None => Signal::Throw(object::Object::new_error(
+                object::ARITHMETIC_ERROR,
+                format!("integer overflow: {} ** {}", left_value, right_value),
+            )),
+        }
+    }
+
+    fn eval_shift_expression(operator: String, left_value: i64, right_value: i64) -> Signal {
+        let shifted = u32::try_from(right_value).ok().and_then(|amount| {
+            if operator == "<<" {
+                left_value.checked_shl(amount)
+            } else {
+                left_value.checked_shr(amount)
+            }
+        });
+
+        match shifted {
+            Some(value) => Signal::Value(Rc::new(object::Object::Integer(value))),
+            None => Signal::Throw(object::Object::new_error(
+                object::ARITHMETIC_ERROR,
+                format!(
+                    "shift amount out of range: {} {} {}",
+                    left_value, operator, right_value
+                ),
+            )),
+        }
+    }
+
+    fn eval_float_infix_expression(operator: String, left_value: f64, right_value: f64) -> Signal {
         match &*operator {
-            "+" => return Some(Rc::new(object::Object::Float(left_value + right_value))),
-            "-" => return Some(Rc::new(object::Object::Float(left_value - right_value))),
-            "*" => return Some(Rc::new(object::Object::Float(left_value * right_value))),
-            "/" => return Some(Rc::new(object::Object::Float(left_value / right_value))),
-            "<" => return Some(Evaluator::eval_boolean(left_value < right_value)),
-            ">" => return Some(Evaluator::eval_boolean(left_value > right_value)),
-            "==" => return Some(Evaluator::eval_boolean(left_value == right_value)),
-            "!=" => return Some(Evaluator::eval_boolean(left_value != right_value)),
+            "+" => return Signal::Value(Rc::new(object::Object::Float(left_value + right_value))),
+            "-" => return Signal::Value(Rc::new(object::Object::Float(left_value - right_value))),
+            "*" => return Signal::Value(Rc::new(object::Object::Float(left_value * right_value))),
+            "/" => return Signal::Value(Rc::new(object::Object::Float(left_value / right_value))),
+            "%" => return Signal::Value(Rc::new(object::Object::Float(left_value % right_value))),
+            "**" => {
+                return Signal::Value(Rc::new(object::Object::Float(left_value.powf(right_value))))
+            }
+            "<" => return Signal::Value(Evaluator::eval_boolean(left_value < right_value)),
+            ">" => return Signal::Value(Evaluator::eval_boolean(left_value > right_value)),
+            "<=" => return Signal::Value(Evaluator::eval_boolean(left_value <= right_value)),
+            ">=" => return Signal::Value(Evaluator::eval_boolean(left_value >= right_value)),
+            "==" => return Signal::Value(Evaluator::eval_boolean(left_value == right_value)),
+            "!=" => return Signal::Value(Evaluator::eval_boolean(left_value != right_value)),
             _ => {
-                return Some(object::Object::new_error(format!(
-                    "unknown operator: {} {} {}",
-                    left_value, operator, right_value
-                )))
+                return Signal::Throw(object::Object::new_error(
+                    object::TYPE_ERROR,
+                    format!(
+                        "unknown operator: {} {} {}",
+                        left_value, operator, right_value
+                    ),
+                ))
             }
         }
     }
@@ -460,30 +1189,49 @@ impl Evaluator {
         operator: String,
         left_value: String,
         right_value: String,
-    ) -> Option<Rc<object::Object>> {
+    ) -> Signal {
         match &*operator {
             "+" => {
-                return Some(Rc::new(object::Object::String(format!(
+                return Signal::Value(Rc::new(object::Object::String(format!(
                     "{}{}",
                     left_value, right_value
                 ))))
             }
-            "==" => return Some(Evaluator::eval_boolean(left_value == right_value)),
-            "!=" => return Some(Evaluator::eval_boolean(left_value != right_value)),
+            "==" => return Signal::Value(Evaluator::eval_boolean(left_value == right_value)),
+            "!=" => return Signal::Value(Evaluator::eval_boolean(left_value != right_value)),
             _ => {
-                return Some(object::Object::new_error(format!(
-                    "unknown operator: STRING {} STRING",
-                    operator
-                )))
+                return Signal::Throw(object::Object::new_error(
+                    object::TYPE_ERROR,
+                    format!("unknown operator: STRING {} STRING", operator),
+                ))
             }
         }
     }
 
-    fn eval_bang_operator_expression(right: Rc<object::Object>) -> Option<Rc<object::Object>> {
+    fn eval_logical_expression(
+        &mut self,
+        operator: String,
+        left: ast::Expression,
+        right: ast::Expression,
+    ) -> Signal {
+        let left = value!(self.eval_expression(left));
+
+        let left_truthy = Evaluator::is_truthy(left);
+        if (operator == "&&" && !left_truthy) || (operator == "||" && left_truthy) {
+            return Signal::Value(Evaluator::eval_boolean(left_truthy));
+        }
+
+        let right = value!(self.eval_expression(right));
+        Signal::Value(Evaluator::eval_boolean(Evaluator::is_truthy(right)))
+    }
+
+    fn eval_bang_operator_expression(right: Rc<object::Object>) -> Signal {
         match *right {
-            object::Object::Boolean(value) => return Some(Evaluator::eval_boolean(!value)),
-            object::Object::Null => return Some(Rc::new(object::TRUE)),
-            _ => Some(Rc::new(object::FALSE)),
+            object::Object::Boolean(value) => {
+                return Signal::Value(Evaluator::eval_boolean(!value))
+            }
+            object::Object::Null => return Signal::Value(Rc::new(object::TRUE)),
+            _ => Signal::Value(Rc::new(object::FALSE)),
         }
     }
 
@@ -494,18 +1242,23 @@ impl Evaluator {
             return Rc::new(object::FALSE);
         }
     }
-    fn eval_minus_prefix_operator_expression(
-        right: Rc<object::Object>,
-    ) -> Option<Rc<object::Object>> {
+    fn eval_minus_prefix_operator_expression(right: Rc<object::Object>) -> Signal {
         match *right {
-            object::Object::Integer(value) => {
-                return Some(Rc::new(object::Object::Integer(-value)))
-            }
+            object::Object::Integer(value) => match value.checked_neg() {
+                Some(value) => return Signal::Value(Rc::new(object::Object::Integer(value))),
+                None => {
+                    return Signal::Throw(object::Object::new_error(
+                        object::ARITHMETIC_ERROR,
+                        format!("integer overflow: -{}", value),
+                    ))
+                }
+            },
+            object::Object::Float(value) => Signal::Value(Rc::new(object::Object::Float(-value))),
             _ => {
-                return Some(object::Object::new_error(format!(
-                    "unknown operator: -{}",
-                    right
-                )))
+                return Signal::Throw(object::Object::new_error(
+                    object::TYPE_ERROR,
+                    format!("unknown operator: -{}", right),
+                ))
             }
         }
     }
@@ -514,20 +1267,14 @@ impl Evaluator {
         condition: Box<ast::Expression>,
         consequence: Box<ast::Statement>,
         alternative: Option<Box<ast::Statement>>,
-    ) -> Option<Rc<object::Object>> {
-        if let Some(evaluated_condition) = self.eval_expression(*condition) {
-            if Evaluator::is_error(&evaluated_condition) {
-                return Some(evaluated_condition);
-            }
-            if Evaluator::is_truthy(evaluated_condition) {
-                return self.eval_statement(*consequence);
-            } else if let Some(alt) = alternative {
-                return self.eval_statement(*alt);
-            } else {
-                return Some(Rc::new(object::NULL));
-            }
+    ) -> Signal {
+        let evaluated_condition = value!(self.eval_expression(*condition));
+        if Evaluator::is_truthy(evaluated_condition) {
+            return self.eval_statement(*consequence);
+        } else if let Some(alt) = alternative {
+            return self.eval_statement(*alt);
         } else {
-            return None;
+            return Signal::Value(Rc::new(object::NULL));
         }
     }
 
@@ -535,49 +1282,136 @@ impl Evaluator {
         &mut self,
         condition: Box<ast::Expression>,
         consequence: Box<ast::Statement>,
-    ) -> Option<Rc<object::Object>> {
+    ) -> Signal {
         let mut object = Rc::new(object::NULL);
         loop {
-            if let Some(evaluated_condition) = self.eval_expression(*condition.clone()) {
-                if Evaluator::is_error(&evaluated_condition) {
-                    return Some(evaluated_condition);
+            let evaluated_condition = value!(self.eval_expression(*condition.clone()));
+            if !Evaluator::is_truthy(evaluated_condition) {
+                break;
+            }
+
+            object = match self.eval_statement(*consequence.clone()) {
+                Signal::Value(obj) => obj,
+                Signal::Break => break,
+                Signal::Continue => continue,
+                signal => return signal,
+            };
+        }
+
+        return Signal::Value(object);
+    }
+
+    // Hashes are walked in key order: integers ascending, then false and
+    // true, then strings alphabetically.
+    fn compare_hash_keys(a: &object::Object, b: &object::Object) -> Ordering {
+        let rank = |key: &object::Object| match key {
+            object::Object::Integer(_) => 0,
+            object::Object::Boolean(_) => 1,
+            _ => 2,
+        };
+        match (a, b) {
+            (object::Object::Integer(a), object::Object::Integer(b)) => a.cmp(b),
+            (object::Object::Boolean(a), object::Object::Boolean(b)) => a.cmp(b),
+            (object::Object::String(a), object::Object::String(b)) => a.cmp(b),
+            _ => rank(a).cmp(&rank(b)),
+        }
+    }
+
+    fn eval_for_expression(
+        &mut self,
+        variables: Vec<ast::Expression>,
+        iterable: ast::Expression,
+        body: ast::Statement,
+    ) -> Signal {
+        let iterable = value!(self.eval_expression(iterable));
+
+        // Every item is a (key, value) pair: the position for arrays, strings
+        // and ranges, the key for hashes. A single loop variable gets the value,
+        // except for hashes where it gets the key.
+        let items: Box<dyn Iterator<Item = (Rc<object::Object>, Rc<object::Object>)>> =
+            match &*iterable {
+                object::Object::Array(elements) => Box::new(
+                    elements
+                        .clone()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, e)| (Rc::new(object::Object::Integer(i as i64)), e)),
+                ),
+                object::Object::Hash(pairs) => {
+                    let mut pairs = pairs
+                        .iter()
+                        .map(|(k, v)| (Rc::clone(k), Rc::clone(v)))
+                        .collect::<Vec<_>>();
+                    pairs.sort_by(|(a, _), (b, _)| Evaluator::compare_hash_keys(a, b));
+                    Box::new(pairs.into_iter())
                 }
-                if !Evaluator::is_truthy(evaluated_condition) {
-                    break;
+                object::Object::String(s) => Box::new(
+                    s.chars()
+                        .map(|c| Rc::new(object::Object::String(c.to_string())))
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, c)| (Rc::new(object::Object::Integer(i as i64)), c)),
+                ),
+                object::Object::Range { start, end } => {
+                    Box::new((*start..*end).enumerate().map(|(i, n)| {
+                        (
+                            Rc::new(object::Object::Integer(i as i64)),
+                            Rc::new(object::Object::Integer(n)),
+                        )
+                    }))
                 }
+                o => {
+                    return Signal::Throw(object::Object::new_error(
+                        object::TYPE_ERROR,
+                        format!("cannot iterate over {}", o),
+                    ))
+                }
+            };
+        let is_hash = matches!(&*iterable, object::Object::Hash(_));
 
-                object = match self.eval_statement(*consequence.clone()) {
-                    Some(obj) => match &*obj {
-                        object::Object::Return(_) => return Some(Rc::clone(&obj)),
-                        _ => obj,
-                    },
-                    _ => {
-                        return None;
-                    }
-                };
-
-                if Evaluator::is_error(&object) {
-                    return Some(object);
+        let outer_env = Rc::clone(&self.env);
+        let mut object = Rc::new(object::NULL);
+        for (key, value) in items {
+            let mut iteration_env =
+                environment::Environment::new_enclosed_environment(Rc::clone(&outer_env));
+            let bindings = match variables.len() {
+                1 if is_hash => vec![key],
+                1 => vec![value],
+                _ => vec![key, value],
+            };
+            for (variable, binding) in variables.iter().zip(bindings) {
+                if let ast::Expression::Identifier { value, .. } = variable {
+                    iteration_env.set(value.to_string(), binding);
                 }
-            } else {
-                return None;
+            }
+
+            self.env = Rc::new(RefCell::new(iteration_env));
+            let evaluated = self.eval_statement(body.clone());
+            self.env = Rc::clone(&outer_env);
+
+            match evaluated {
+                Signal::Value(evaluated) => object = evaluated,
+                Signal::Break => break,
+                Signal::Continue => continue,
+                signal => return signal,
             }
         }
 
-        return Some(object);
+        Signal::Value(object)
     }
 
-    fn eval_identifier(&mut self, ident: String) -> Option<Rc<object::Object>> {
+    fn eval_identifier(&mut self, ident: String) -> Signal {
         if let Some(value) = self.env.borrow_mut().get((&ident).to_string()) {
-            return Some(value);
+            return Signal::Value(value);
         }
         if let Some(value) = self.builtin.get(&ident) {
-            return Some(Rc::clone(value));
+            return Signal::Value(Rc::clone(value));
         }
-        return Some(object::Object::new_error(format!(
-            "identifier not found: {}",
-            ident
-        )));
+        return Signal::Throw(object::Object::new_error(
+            object::NAME_ERROR,
+            format!("identifier not found: {}", ident),
+        ));
     }
     fn is_truthy(obj: Rc<object::Object>) -> bool {
         match *obj {
@@ -586,10 +1420,45 @@ impl Evaluator {
             _ => return true,
         }
     }
-    fn is_error(obj: &Rc<object::Object>) -> bool {
-        match **obj {
-            object::Object::Error(_) => return true,
-            _ => return false,
+
+    fn locate_error(obj: Rc<object::Object>, span: token::Span) -> Rc<object::Object> {
+        match &*obj {
+            object::Object::Error {
+                message,
+                kind,
+                span: None,
+                trace,
+            } => Rc::new(object::Object::Error {
+                message: message.clone(),
+                kind: kind.clone(),
+                span: Some(span),
+                trace: trace.clone(),
+            }),
+            _ => obj,
+        }
+    }
+
+    // Records the calls in progress on an error leaving a function. The
+    // first function it leaves sees the whole stack.
+    fn trace_error(&self, err: Rc<object::Object>) -> Rc<object::Object> {
+        match &*err {
+            object::Object::Error {
+                message,
+                kind,
+                span,
+                trace,
+            } if trace.is_empty() => Rc::new(object::Object::Error {
+                message: message.clone(),
+                kind: kind.clone(),
+                span: span.clone(),
+                trace: self
+                    .frames
+                    .iter()
+                    .rev()
+                    .map(|frame| (frame.function.clone(), frame.call_site.clone()))
+                    .collect(),
+            }),
+            _ => err,
         }
     }
 }
@@ -609,6 +1478,7 @@ mod evaluator_tests {
                 ("-10", -10),
                 ("5 + 5 + 5 + 5 - 10", 10),
                 ("2 * 2 * 2 * 2 * 2", 32),
+                ("(0 - 9223372036854775807 - 1) % -1", 0),
                 ("-50 + 100 + -50", 0),
                 ("5 * 2 + 10", 20),
                 ("5 + 2 * 10", 25),
@@ -618,6 +1488,17 @@ mod evaluator_tests {
                 ("3 * 3 * 3 + 10", 37),
                 ("3 * (3 * 3) + 10", 37),
                 ("(5 + 10 * 2 + 15 / 3) * 2 + -10", 50),
+                ("7 % 3", 1),
+                ("-7 % 3", -1),
+                ("2 ** 10", 1024),
+                ("2 ** 3 ** 2", 512),
+                ("-2 ** 2", -4),
+                ("6 & 3", 2),
+                ("6 | 3", 7),
+                ("6 ^ 3", 5),
+                ("1 << 4", 16),
+                ("-16 >> 2", -4),
+                ("1 + 2 << 1", 6),
             ]
         );
 
@@ -627,12 +1508,60 @@ mod evaluator_tests {
         }
     }
 
+    #[test]
+    fn test_eval_float_expression() {
+        counted_array!(
+            let tests: [(&str, f64, &str); _] = [
+                ("1.05", 1.05, "1.05"),
+                ("1.0", 1.0, "1.0"),
+                ("1e-3", 0.001, "0.001"),
+                ("-2.5", -2.5, "-2.5"),
+                ("1.5 + 1", 2.5, "2.5"),
+                ("2 * 0.25", 0.5, "0.5"),
+                ("1.5e2 / 3", 50.0, "50.0"),
+            ]
+        );
+
+        for t in tests {
+            let evaluated = test_eval(t.0.to_string());
+            if let object::Object::Float(value) = &*evaluated {
+                assert_eq!(*value, t.1);
+            } else {
+                panic!("{} is not float object.", evaluated);
+            }
+            assert_eq!(evaluated.string(), t.2);
+        }
+    }
+
+    #[test]
+    fn test_eval_float_operators() {
+        counted_array!(
+            let tests: [(&str, f64); _] = [
+                ("7.5 % 2", 1.5),
+                ("2.0 ** 3", 8.0),
+                ("2 ** -1", 0.5),
+                ("4 ** 0.5", 2.0),
+            ]
+        );
+
+        for t in tests {
+            let evaluated = test_eval(t.0.to_string());
+            if let object::Object::Float(value) = &*evaluated {
+                assert_eq!(*value, t.1);
+            } else {
+                panic!("{} is not float object.", evaluated);
+            }
+        }
+    }
+
     #[test]
     fn test_eval_string_literal() {
         counted_array!(
             let tests: [(&str, &str); _] = [
                 ("\"Hello World!\"", "Hello World!"),
                 ("\"Hello\" + \" \" + \"World!\"", "Hello World!"),
+                ("\"say \\\"hi\\\"\\n\"", "say \"hi\"\n"),
+                ("\"\\u{48}i\\t\"", "Hi\t"),
             ]
         );
 
@@ -671,6 +1600,22 @@ mod evaluator_tests {
                 ("(1 > 2) == false", true),
                 ("\"Hello\" == \"Hello\"", true),
                 ("\"Hello\" == \"World\"", false),
+                ("1 <= 1", true),
+                ("2 <= 1", false),
+                ("1 >= 2", false),
+                ("2.5 >= 2.5", true),
+                ("1.5 <= 1", false),
+                ("true && true", true),
+                ("true && false", false),
+                ("false || true", true),
+                ("false || false", false),
+                ("1 < 2 && 2 < 3", true),
+                ("1 > 2 || 2 > 3", false),
+                ("true & false", false),
+                ("true | false", true),
+                ("true ^ true", false),
+                ("false && undefined", false),
+                ("true || undefined", true),
 
             ]
         );
@@ -712,6 +1657,12 @@ mod evaluator_tests {
                 ("if (1 > 2) { 10 } else { 20 }", Some(20)),
                 ("if (1 < 2) { 10 } else { 20 }", Some(10)),
                 ("if (1 < 2) { 10 } else { 20 }", Some(10)),
+                ("if (1 > 2) { 10 } else if (1 < 2) { 20 }", Some(20)),
+                ("if (1 > 2) { 10 } else if (1 > 2) { 20 }", None),
+                ("if (1 > 2) { 10 } else if (1 > 2) { 20 } else { 30 }", Some(30)),
+                ("if (1 < 2) { 10 } else if (1 < 2) { 20 } else { 30 }", Some(10)),
+                ("let x = 3; if (x == 1) { 1 } else if (x == 2) { 2 } else if (x == 3) { 3 } else { 4 }", Some(3)),
+                ("let f = fn(x) { if (x < 0) { return -1; } else if (x == 0) { return 0; } 1 }; f(0)", Some(0)),
             ]
         );
 
@@ -731,6 +1682,32 @@ mod evaluator_tests {
             let tests: [(&str, Option<i64>); _] = [
                 ("let i = 0; while (i < 10) { let i = i + 1; }", Some(10)),
                 ("let i = 0; while (true) { let i = i + 1; if (i == 10) { return i; } }", Some(10)),
+                ("let i = 0; let sum = 0; while (true) { let i = i + 1; if (i > 5) { break; } let sum = sum + i; } sum", Some(15)),
+                ("let i = 0; let sum = 0; while (i < 10) { let i = i + 1; if (i % 2 == 0) { continue; } let sum = sum + i; } sum", Some(25)),
+                ("let i = 0; let n = 0; while (i < 3) { let i = i + 1; let j = 0; while (true) { let j = j + 1; if (j > 2) { break; } let n = n + 1; } } n", Some(6)),
+            ]
+        );
+
+        for t in tests {
+            let evaluated = test_eval(t.0.to_string());
+            if let Some(integ) = t.1 {
+                test_integer_object(&evaluated, integ);
+            } else {
+                test_null_object(&*evaluated);
+            }
+        }
+    }
+
+    #[test]
+    fn test_for_expression() {
+        counted_array!(
+            let tests: [(&str, Option<i64>); _] = [
+                ("for (x in [1, 2, 3]) { x * 2 }", Some(6)),
+                ("for (x in []) { x }", None),
+                ("for (i in range(0)) { i }", None),
+                ("let find = fn(xs, y) { for (i, x in xs) { if (x == y) { return i; } }; -1 }; find([4, 5, 6], 6)", Some(2)),
+                ("let f = for (i in range(3)) { if (i == 1) { break; } fn() { i } }; f()", Some(0)),
+                ("let i = 10; for (i in range(3)) { i }; i", Some(10)),
             ]
         );
 
@@ -744,6 +1721,425 @@ mod evaluator_tests {
         }
     }
 
+    #[test]
+    fn test_index_assignment() {
+        counted_array!(
+            let tests: [(&str, i64); _] = [
+                ("let a = [1, 2, 3]; a[0] = 10; a[0] + a[1]", 12),
+                ("let a = [1, 2, 3]; a[2] = 5", 5),
+                ("let h = {\"k\": 1}; h[\"k\"] = 2; h[\"k\"]", 2),
+                ("let h = {}; h[true] = 3; h[true]", 3),
+                ("let m = {\"a\": [0, 1, 2]}; m[\"a\"][2] = 7; m[\"a\"][2]", 7),
+                ("let g = [[1, 2], [3, 4]]; g[1][0] = 9; g[1][0] + g[0][0]", 10),
+                ("let i = 0; let a = [1, 2]; a[i + 1] = 4; a[1]", 4),
+                ("let a = [1, 2]; let b = a; a[0] = 5; b[0]", 1),
+                ("let m = {\"a\": [0]}; let inner = m[\"a\"]; m[\"a\"][0] = 8; inner[0]", 0),
+            ]
+        );
+
+        for t in tests {
+            test_integer_object(&test_eval(t.0.to_string()), t.1);
+        }
+    }
+
+    #[test]
+    fn test_assignment_scope() {
+        counted_array!(
+            let tests: [(&str, i64); _] = [
+                ("let counter = fn() { let count = 0; fn() { count = count + 1; count } }; let c = counter(); c(); c(); c()", 3),
+                ("let x = 1; let f = fn() { x = 5 }; let g = fn() { x }; f(); g()", 5),
+                ("let x = 1; let f = fn() { let x = 2; x = 3; x }; let g = fn() { x }; f(); g()", 1),
+                ("let sum = 0; for (x in [1, 2, 3]) { sum = sum + x; } sum", 6),
+                ("let i = 0; while (i < 5) { i = i + 1; } i", 5),
+                ("let a = [0, 0]; let f = fn(i) { a[i] = 1 }; let g = fn() { a[0] + a[1] }; f(1); g()", 1),
+            ]
+        );
+
+        for t in tests {
+            test_integer_object(&test_eval(t.0.to_string()), t.1);
+        }
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("let x = 5; x += 2; x", "7"),
+                ("let x = 5; x -= 2; x", "3"),
+                ("let x = 5; x *= 2; x", "10"),
+                ("let x = 5; x /= 2; x", "2"),
+                ("let x = 5; x %= 2; x", "1"),
+                ("let x = 1.5; x *= 2; x", "3.0"),
+                ("let x = 5; x += 2", "7"),
+                ("let s = \"ab\"; s += \"cd\"; s", "\"abcd\""),
+                ("let a = [1, 2]; a[1] *= 10; a[1]", "20"),
+                ("let m = {\"a\": [1]}; m[\"a\"][0] += 4; m[\"a\"][0]", "5"),
+                ("let i = 0; while (i < 5) { i += 1; } i", "5"),
+                ("let n = 1; let f = fn() { n += 1 }; let g = fn() { n }; f(); f(); g()", "3"),
+            ]
+        );
+
+        for t in tests {
+            assert_eq!(test_eval(t.0.to_string()).string(), t.1, "{}", t.0);
+        }
+    }
+
+    #[test]
+    fn test_let_destructuring() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("let [a, b] = [1, 2]; a * 10 + b", "12"),
+                ("let [a, ...rest] = [1, 2, 3]; rest", "[2, 3, ]"),
+                ("let [a, b, ...rest] = [1, 2]; rest", "[]"),
+                ("let [...all] = [1, 2]; all", "[1, 2, ]"),
+                ("let [[a, b], c] = [[1, 2], 3]; [a, b, c]", "[1, 2, 3, ]"),
+                ("let {name, age} = {\"name\": \"Ann\", \"age\": 30}; [name, age]", "[\"Ann\", 30, ]"),
+                ("let {x} = {\"x\": 1, \"y\": 2}; x", "1"),
+                ("let [{x}, [y]] = [{\"x\": 1}, [2]]; x + y", "3"),
+                ("let pair = fn() { [1, 2] }; let [a, b] = pair(); a + b", "3"),
+                ("let a = 1; let b = 2; let [a, b] = [b, a]; [a, b]", "[2, 1, ]"),
+                ("let [a, b] = [1, 2]", "[1, 2, ]"),
+            ]
+        );
+
+        for t in tests {
+            assert_eq!(test_eval(t.0.to_string()).string(), t.1, "{}", t.0);
+        }
+    }
+
+    #[test]
+    fn test_function_arguments() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("let f = fn(x, y = 2) { x * y }; f(3)", "6"),
+                ("let f = fn(x, y = 2) { x * y }; f(3, 4)", "12"),
+                ("let f = fn(x, y = x + 1) { y }; f(3)", "4"),
+                ("let f = fn(...xs) { xs }; f()", "[]"),
+                ("let f = fn(...xs) { xs }; f(1, 2)", "[1, 2, ]"),
+                ("let f = fn(x, ...xs) { len(xs) }; f(1, 2, 3)", "2"),
+                ("let f = fn(x, y) { x - y }; f(y: 1, x: 5)", "4"),
+                ("let f = fn(x, y = 2, z = 3) { [x, y, z] }; f(1, z: 30)", "[1, 2, 30, ]"),
+                ("let f = fn(x = 1, y) { x + y }; f(y: 10)", "11"),
+                ("let f = fn(x, ...rest) { [x, rest] }; f(x: 1)", "[1, [], ]"),
+                ("let n = 0; let f = fn(x = (n = n + 1)) { x }; f(); f(); n", "2"),
+                ("let n = 0; let f = fn(x = (n = n + 1)) { x }; f(7); n", "0"),
+            ]
+        );
+
+        for t in tests {
+            assert_eq!(test_eval(t.0.to_string()).string(), t.1, "{}", t.0);
+        }
+    }
+
+    #[test]
+    fn test_function_declarations() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("fn add(x, y) { x + y } add(1, 2)", "3"),
+                ("let r = double(4); fn double(x) { x * 2 } r", "8"),
+                ("fn even(n) { if (n == 0) { true } else { odd(n - 1) } }
+                  fn odd(n) { if (n == 0) { false } else { even(n - 1) } }
+                  even(10)", "true"),
+                ("let f = fn() { let r = inner(); fn inner() { 1 } r }; f()", "1"),
+                ("fn fact(n) { if (n < 2) { 1 } else { n * fact(n - 1) } } fact(5)", "120"),
+                ("fn id(x) { x } id", "fn id(x) {\r\n\tx\r\n}"),
+                ("fn(x) { x }", "fn (x) {\r\n\tx\r\n}"),
+                ("fn f() { 1 }", "NULL"),
+                ("fn f() { 1 } f = 2; f", "2"),
+            ]
+        );
+
+        for t in tests {
+            assert_eq!(test_eval(t.0.to_string()).string(), t.1, "{}", t.0);
+        }
+    }
+
+    #[test]
+    fn test_match_expression() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("match (1) { 1 => \"one\", _ => \"many\" }", "\"one\""),
+                ("match (5) { 1 => \"one\", _ => \"many\" }", "\"many\""),
+                ("match (-2) { -2 => 1, _ => 2 }", "1"),
+                ("match (1.5) { 1 => 1, 1.5 => 2, _ => 3 }", "2"),
+                ("match (\"b\") { \"a\" => 1, \"b\" => 2 }", "2"),
+                ("match (false) { true => 1, false => 2 }", "2"),
+                ("match (7) { n => n * 2 }", "14"),
+                ("match (5) { null => \"none\", _ => \"some\" }", "\"some\""),
+                ("match (puts()) { null => \"none\", _ => \"some\" }", "\"none\""),
+                ("match ([1, puts()]) { [x, null] => x }", "1"),
+                ("match (7) { n if n > 10 => 1, n if n > 5 => 2, _ => 3 }", "2"),
+                ("match ([1, 2]) { [] => 0, [a] => a, [a, b] => a + b }", "3"),
+                ("match ([1, 2, 3]) { [first, ...rest] => rest }", "[2, 3, ]"),
+                ("match ([1, [2, 3]]) { [1, [_, x]] => x }", "3"),
+                ("match ([1]) { [a, ...rest] => len(rest) }", "0"),
+                (
+                    "let area = fn(s) {
+                        match (s) {
+                            {kind: \"square\", side} => side * side,
+                            {kind: \"rect\", w, h} => w * h,
+                        }
+                    };
+                    area({\"kind\": \"rect\", \"w\": 2, \"h\": 3})",
+                    "6",
+                ),
+                ("match ({\"a\": [1, 2]}) { {a: [x, y]} => x + y }", "3"),
+                ("match (1) { 1 => { let y = 2; y * 3 } }", "6"),
+                ("let x = 1; match (5) { x => x }; x", "1"),
+                ("let f = fn(n) { match (n) { 0 => { return 10; } _ => 20 }; 30 }; f(0)", "10"),
+                ("let f = fn(n) { match (n) { 0 => { return 10; } _ => 20 }; 30 }; f(1)", "30"),
+                ("let r = []; for (i in range(4)) { match (i) { 2 => { break; } _ => 0 }; r = push(r, i) }; r", "[0, 1, ]"),
+            ]
+        );
+
+        for t in tests {
+            assert_eq!(test_eval(t.0.to_string()).string(), t.1, "{}", t.0);
+        }
+    }
+
+    #[test]
+    fn test_try_expression() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("try { 1 } catch (e) { 2 }", "1"),
+                ("try { throw \"boom\" } catch (e) { e[\"message\"] }", "\"boom\""),
+                ("try { throw \"boom\" } catch (e) { e[\"kind\"] }", "\"Error\""),
+                ("try { throw 42 } catch (e) { e[\"message\"] }", "\"42\""),
+                ("try { [1, 2][5] } catch (e) { e[\"message\"] }", "\"list index out of range\""),
+                ("try { [1, 2][5] } catch (e) { e[\"kind\"] }", "\"IndexError\""),
+                ("try { 1 / 0 } catch (e) { e[\"kind\"] }", "\"ArithmeticError\""),
+                ("try { nope } catch (e) { e[\"kind\"] }", "\"NameError\""),
+                ("try { len(1, 2) } catch (e) { e[\"kind\"] }", "\"ArgumentError\""),
+                ("try { exit(4294967296) } catch (e) { e[\"kind\"] }", "\"ArgumentError\""),
+                ("try { exit(4294967296) } catch (e) { e[\"message\"] }", "\"exit status out of range, got 4294967296\""),
+                ("try { first() } catch (e) { e[\"kind\"] }", "\"ArgumentError\""),
+                ("try { last() } catch (e) { e[\"kind\"] }", "\"ArgumentError\""),
+                ("try { rest() } catch (e) { e[\"kind\"] }", "\"ArgumentError\""),
+                ("try { push([1]) } catch (e) { e[\"message\"] }", "\"wrong number of arguments. got=1, want=2\""),
+                ("try { exit(\"x\") } catch (e) { e[\"message\"] }", "\"argument to `exit` not supported, got STRING\""),
+                ("try { import(1) } catch (e) { e[\"message\"] }", "\"argument to `import` not supported, got INTEGER\""),
+                ("try {\n  1 + true\n} catch (e) { e[\"location\"] }", "\"2:3\""),
+                ("try {\n  throw \"x\"\n} catch (e) { e[\"location\"] }", "\"2:3\""),
+                (
+                    "try { throw {\"message\": \"too big\", \"kind\": \"RangeError\"} }
+                    catch ({message, kind}) { kind + \": \" + message }",
+                    "\"RangeError: too big\"",
+                ),
+                ("let f = fn() { throw \"inner\"; 1 }; try { f() } catch (e) { e[\"message\"] }", "\"inner\""),
+                ("try { try { throw \"a\" } catch (e) { throw e[\"message\"] + \"b\" } } catch (e) { e[\"message\"] }", "\"ab\""),
+                ("let log = []; try { log = push(log, 1) } finally { log = push(log, 2) }; log", "[1, 2, ]"),
+                ("let log = []; try { throw 1 } catch (e) { log = push(log, 1) } finally { log = push(log, 2) }; log", "[1, 2, ]"),
+                ("try { 1 } finally { 2 }", "1"),
+                ("let f = fn() { try { return 1 } finally { return 2 } }; f()", "2"),
+                ("let f = fn() { try { throw \"x\" } finally { return 2 } }; f()", "2"),
+                ("let f = fn() { try { return 1 } catch (e) { 2 } }; f()", "1"),
+                ("let r = []; for (i in range(3)) { try { if (i == 1) { throw i } r = push(r, i) } catch (e) { continue } }; r", "[0, 2, ]"),
+                ("let e = 1; try { throw 2 } catch (e) { e }; e", "1"),
+            ]
+        );
+
+        for t in tests {
+            assert_eq!(test_eval(t.0.to_string()).string(), t.1, "{}", t.0);
+        }
+    }
+
+    #[test]
+    fn test_signals() {
+        let eval = |input: &str| {
+            let l = lexer::Lexer::new(input);
+            let mut p = parser::Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(&p);
+            Evaluator::new().eval_program(program)
+        };
+
+        counted_array!(
+            let exits: [(&str, i32); _] = [
+                ("exit()", 0),
+                ("exit(3)", 3),
+                ("let a = 1; exit(2); a", 2),
+                ("let f = fn() { while (true) { exit(4) } }; f(); 1", 4),
+                ("for (i in range(5)) { try { exit(i) } catch (e) { 0 } }", 0),
+                ("try { 1 } finally { exit(5) }", 5),
+            ]
+        );
+        for (input, expected) in exits {
+            match eval(input) {
+                Signal::Exit(code) => assert_eq!(code, expected, "{}", input),
+                _ => panic!("{} did not exit", input),
+            }
+        }
+
+        // A `return` ends its function, whatever expression it appears in.
+        counted_array!(
+            let values: [(&str, &str); _] = [
+                ("let f = fn() { [1, if (true) { return 2; }] }; f()", "2"),
+                ("let f = fn() { let x = if (true) { return 3; }; 4 }; f()", "3"),
+                ("let f = fn() { {\"a\": match (1) { _ => { return 5; } }} }; f()", "5"),
+                ("return 6; 7", "6"),
+                ("exit", "builtin-functions"),
+                ("let exit = 8; exit", "8"),
+            ]
+        );
+        for (input, expected) in values {
+            match eval(input) {
+                Signal::Value(value) => assert_eq!(value.string(), expected, "{}", input),
+                _ => panic!("{} did not produce a value", input),
+            }
+        }
+    }
+
+    #[test]
+    fn test_call_frames() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("let f = fn(x) { x }; f(1); x", "identifier not found: x"),
+                ("let f = fn() { let y = 2; y }; f(); y", "identifier not found: y"),
+                ("let f = fn() { let y = 2; y }; f(); let z = 3; f(); z", "3"),
+                ("let inner = fn(a) { a }; let outer = fn(b) { inner(b); b }; outer(5)", "5"),
+                ("let inner = fn(a) { a }; let outer = fn(b) { inner(1); let c = b; c + a }; outer(5)", "identifier not found: a"),
+                ("let x = 1; let f = fn() { let x = 2; x }; f() + x", "3"),
+                ("let f = fn(n) { if (n == 0) { return 0; } let m = n; f(n - 1); m }; f(3)", "3"),
+                ("let f = fn() { return 1; }; f(); let g = 2; g", "2"),
+                ("let f = fn() { let y = 1; nope }; try { f() } catch (e) { 0 }; y", "identifier not found: y"),
+                ("let f = fn() { let y = 1; throw \"x\" }; try { f() } catch (e) { 0 }; let z = 2; z", "2"),
+                ("let f = fn() { let y = 1; len(1) }; try { f() } catch (e) { 0 }; y", "identifier not found: y"),
+                ("let f = fn(x = g()) { x }; let g = fn() { let q = 1; q }; f(); q", "identifier not found: q"),
+                ("fn a(n) { let k = n; b(n) + k } fn b(n) { let j = n * 10; j } a(1); k", "identifier not found: k"),
+                ("for (i in range(2)) { let f = fn() { let w = i; w }; f() }; w", "identifier not found: w"),
+            ]
+        );
+
+        for t in tests {
+            let mut evaluator = Evaluator::new();
+            let l = lexer::Lexer::new(t.0);
+            let mut p = parser::Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(&p);
+
+            let evaluated = match evaluator.eval_program(program) {
+                Signal::Value(obj) | Signal::Throw(obj) => obj,
+                _ => panic!("{}", t.0),
+            };
+            match &*evaluated {
+                object::Object::Error { message, .. } => assert_eq!(message, t.1, "{}", t.0),
+                _ => assert_eq!(evaluated.string(), t.1, "{}", t.0),
+            }
+            assert!(evaluator.frames.is_empty(), "{}", t.0);
+        }
+    }
+
+    #[test]
+    fn test_stack_traces() {
+        counted_array!(
+            let tests: [(&str, Vec<&str>); _] = [
+                ("1 / 0", vec![]),
+                ("let f = fn() { 1 / 0 };\nf()", vec!["f 2:1"]),
+                ("fn f(n) { if (n == 0) { nope } f(n - 1) }\nf(2)", vec!["f 1:32", "f 1:32", "f 2:1"]),
+                ("let g = fn() { len(1) };\nlet h = g;\n  h()", vec!["h 3:3"]),
+                ("fn g() { len(1) }\nlet h = g;\nh()", vec!["g 3:1"]),
+                ("let f = fn() { try { nope } catch (e) { 0 } };\nf(); nope", vec![]),
+                ("let f = fn() { try { nope } catch (e) { throw e[\"message\"] } };\nlet g = fn() { f() };\ng()", vec!["f 2:16", "g 3:1"]),
+                ("let f = fn(x = nope) { x };\nf()", vec!["f 2:1"]),
+            ]
+        );
+
+        for t in tests {
+            match &*test_eval(t.0.to_string()) {
+                object::Object::Error { trace, .. } => {
+                    let trace: Vec<String> = trace
+                        .iter()
+                        .map(|(function, call_site)| format!("{} {}", function, call_site))
+                        .collect();
+                    assert_eq!(trace, t.1, "{}", t.0);
+                }
+                _ => panic!("no error from {}", t.0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_call_depth() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(1999)", "1999"),
+                ("let f = fn(n) { f(n + 1) }; try { f(0) } catch (e) { e[\"kind\"] }", "\"RecursionError\""),
+                ("let f = fn(n) { f(n + 1) }; try { f(0) } catch (e) { e[\"message\"] }", "\"maximum call depth exceeded\""),
+                ("let f = fn(n) { f(n + 1) }; try { f(0) } catch (e) { e[\"location\"] }", "\"1:17\""),
+                ("let f = fn() { let g = fn(n) { g(n + 1) }; try { g(0) } catch (e) { 1 } }; f() + f()", "2"),
+                ("let f = fn(x = f()) { x }; try { f() } catch (e) { e[\"kind\"] }", "\"RecursionError\""),
+                ("fn f(x = f()) { x } try { f() } catch (e) { 0 }", "0"),
+            ]
+        );
+
+        // Deep recursion needs the stack the interpreter itself runs on.
+        for (input, expected) in tests {
+            let evaluated = std::thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn(move || test_eval(input.to_string()).string())
+                .unwrap()
+                .join()
+                .unwrap();
+            assert_eq!(evaluated, expected, "{}", input);
+        }
+
+        let traced = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(
+                || match &*test_eval("let f = fn(n) { f(n + 1) };\nf(0)".to_string()) {
+                    object::Object::Error { trace, .. } => {
+                        (trace.len(), trace.last().unwrap().1.to_string())
+                    }
+                    _ => panic!("no error from deep recursion"),
+                },
+            )
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(traced, (MAX_CALL_DEPTH, "2:1".to_string()));
+    }
+
+    #[test]
+    fn test_import_cycles() {
+        let dir = std::env::temp_dir().join(format!("strainer_imports_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).display().to_string();
+        let files = [
+            ("self.str", format!("import(\"{}\")", path("self.str"))),
+            ("a.str", format!("import(\"{}\")", path("b.str"))),
+            ("b.str", format!("import(\"{}\")", path("a.str"))),
+            ("lib.str", "let answer = 42; answer".to_string()),
+        ];
+        for (name, source) in &files {
+            std::fs::write(path(name), source).unwrap();
+        }
+
+        counted_array!(
+            let tests: [(String, String); _] = [
+                (
+                    format!("try {{ import(\"{}\") }} catch (e) {{ e[\"kind\"] }}", path("self.str")),
+                    "\"ImportError\"".to_string(),
+                ),
+                (
+                    format!("try {{ import(\"{}\") }} catch (e) {{ e[\"message\"] }}", path("a.str")),
+                    format!(
+                        "\"circular import of {}\"",
+                        std::fs::canonicalize(path("a.str")).unwrap().display()
+                    ),
+                ),
+                (
+                    format!("import(\"{0}\"); import(\"{0}\") + answer", path("lib.str")),
+                    "84".to_string(),
+                ),
+            ]
+        );
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input.clone()).string(), expected, "{}", input);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_return_statements() {
         counted_array!(
@@ -789,14 +2185,77 @@ mod evaluator_tests {
                 ("len(\"one\", \"two\")",  "wrong number of arguments. got=2, want=1"),
                 ("[1, 2, 3][3]", "list index out of range"),
                 ("[1, 2, 3][-1]", "list index out of range"),
+                ("1 / 0", "division by zero"),
+                ("1 % 0", "division by zero"),
+                ("2 ** 64", "integer overflow: 2 ** 64"),
+                ("1 << 64", "shift amount out of range: 1 << 64"),
+                ("1 >> -1", "shift amount out of range: 1 >> -1"),
+                ("true && undefined", "identifier not found: undefined"),
+                ("1.5 & 2.5", "unknown operator: 1.5 & 2.5"),
+                ("(1 + true) + (2 + \"a\")", "type mismatch: INTEGER + BOOLEAN"),
+                ("add(one, two)", "identifier not found: add"),
+                ("len(one, two)", "identifier not found: one"),
+                ("{ one: two }", "identifier not found: one"),
+                ("for (x in 5) { x }", "cannot iterate over INTEGER"),
+                ("for (i in range(3)) { i }; i", "identifier not found: i"),
+                ("range(1, true)", "argument to `range` must be INTEGER, got BOOLEAN"),
+                ("let a = [1]; a[1] = 2", "list index out of range"),
+                ("let a = [1]; a[-1] = 2", "list index out of range"),
+                ("let a = [1]; a[\"x\"] = 2", "array index must be INTEGER, got STRING"),
+                ("let h = {}; h[[1]] = 2", "unusable as hash key: ARRAY"),
+                ("let h = {}; h[\"a\"][0] = 2", "key not found: \"a\""),
+                ("let n = 5; n[0] = 1", "index assignment not supported: INTEGER"),
+                ("let a = [1, [2]]; a[0][0] = 1", "index assignment not supported: INTEGER"),
+                ("b[0] = 1", "identifier not found: b"),
+                ("[1][0] = 2", "invalid assignment target: [1]"),
+                ("1 = 2", "invalid assignment target: 1"),
+                ("let f = fn() { y = 1 }; f()", "y is not defined before."),
+                ("y += 1", "identifier not found: y"),
+                ("let s = \"a\"; s -= \"b\"", "unknown operator: STRING - STRING"),
+                ("let x = 1; x /= 0", "division by zero"),
+                ("let h = {}; h[\"k\"] += 1", "key not found: \"k\""),
+                ("{\"a\": 1}[\"b\"]", "key not found: \"b\""),
+                ("5[0]", "index operator not supported: INTEGER[INTEGER]"),
+                ("let [a, b] = [1]", "[a, b] expects 2 elements, got 1"),
+                ("let f = fn(x) { x }; f(1, 2)", "wrong number argument. got=2, expected=1"),
+                ("let f = fn(x, y = 1) { x }; f()", "missing argument: x"),
+                ("let f = fn(x) { x }; f(y: 1)", "unknown argument name: y"),
+                ("let f = fn(x) { x }; f(1, x: 2)", "argument x given more than once"),
+                ("let f = fn(...xs) { xs }; f(xs: [])", "unknown argument name: xs"),
+                ("let f = fn(x = y) { x }; f()", "identifier not found: y"),
+                ("len(x: 1)", "builtin functions do not take named arguments"),
+                ("match (3) { 1 => 1, 2 => 2 }", "no match arm matches 3"),
+                ("match ([1]) { [a, b] => 1 }", "no match arm matches [1, ]"),
+                ("match (1) { n if n > 1 => n }", "no match arm matches 1"),
+                ("match (1) { n if m => n }", "identifier not found: m"),
+                ("match (nope) { _ => 1 }", "identifier not found: nope"),
+                ("let {a: [b, c]} = {\"a\": [1]}", "[b, c] expects 2 elements, got 1"),
+                ("fn add(x, y) { x + y } add(1)", "add: missing argument: y"),
+                ("fn add(x, y) { x + y } add(1, 2, 3)", "add: wrong number argument. got=3, expected=2"),
+                ("let [a] = [1, 2]", "[a] expects 1 elements, got 2"),
+                ("let [a, b, ...c] = [1]", "[a, b, ...c] expects at least 2 elements, got 1"),
+                ("let [a, [b]] = [1, 2]", "cannot destructure INTEGER as an array"),
+                ("let {a} = [1]", "cannot destructure ARRAY as a hash"),
+                ("let {a, b} = {\"a\": 1}", "key not found: \"b\""),
+                ("9223372036854775807 + 1", "integer overflow: 9223372036854775807 + 1"),
+                ("0 - 9223372036854775807 - 2", "integer overflow: -9223372036854775807 - 2"),
+                ("4611686018427387904 * 2", "integer overflow: 4611686018427387904 * 2"),
+                ("(0 - 9223372036854775807 - 1) / -1", "integer overflow: -9223372036854775808 / -1"),
+                ("-(0 - 9223372036854775807 - 1)", "integer overflow: --9223372036854775808"),
+                ("try { 9223372036854775807 + 1 } catch (e) { throw e[\"kind\"] }", "ArithmeticError"),
+                ("throw \"boom\"", "boom"),
+                ("throw [1]", "[1, ]"),
+                ("try { throw \"a\" } finally { 1 }", "a"),
+                ("try { 1 } finally { throw \"b\" }", "b"),
+                ("try { throw 1 } catch ([a]) { a }", "cannot destructure HASH as an array"),
             ]
         );
 
         for t in tests {
             let evaluated = test_eval(t.0.to_string());
             match &*evaluated {
-                object::Object::Error(value) => {
-                    assert_eq!(value, t.1);
+                object::Object::Error { message, .. } => {
+                    assert_eq!(message, t.1);
                 }
                 _ => {
                     panic!("{}", evaluated);
@@ -805,6 +2264,27 @@ mod evaluator_tests {
         }
     }
 
+    #[test]
+    fn test_error_positions() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("foobar", "1:1: identifier not found: foobar"),
+                ("let a = 1;\nlet b = a + true;", "2:9: type mismatch: INTEGER + BOOLEAN"),
+                ("let f = fn(x) {\n  x + y;\n};\nf(1);", "2:7: identifier not found: y"),
+                ("let a = [1, 2];\n  a[5]", "2:3: list index out of range"),
+                ("len(1)", "1:1: argument to `len` not supported, got INTEGER"),
+                ("let [a, [b, c]] =\n  [1, [2]];", "1:9: [b, c] expects 2 elements, got 1"),
+                ("let {a, b} = {};", "1:6: key not found: \"a\""),
+                ("let x = 1;\n  throw \"bad \" + \"x\";", "2:3: bad x"),
+            ]
+        );
+
+        for t in tests {
+            let evaluated = test_eval(t.0.to_string());
+            assert_eq!(evaluated.string(), t.1);
+        }
+    }
+
     #[test]
     fn test_function_object() {
         counted_array!(
@@ -817,9 +2297,7 @@ mod evaluator_tests {
             let evaluated = test_eval(t.0.to_string());
             match &*evaluated {
                 object::Object::Function {
-                    parameters,
-                    body,
-                    env: _,
+                    parameters, body, ..
                 } => {
                     assert_eq!(parameters.len(), t.1.len());
                     for (i, p) in parameters.iter().enumerate() {
@@ -892,6 +2370,7 @@ mod evaluator_tests {
                 ("[1,2,3][1]", 2),
                 ("[1,2,3][2]", 3),
                 ("let i = 0; [1][i];", 1),
+                ("let row_2 = [4, 5]; let _i = 1; row_2[_i];", 5),
                 ("[1, 2, 3][1 + 1]", 3),
                 ("let myArray = [1, 2, 3]; myArray[2];", 3),
                 ("let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];", 6),
@@ -997,23 +2476,86 @@ mod evaluator_tests {
         }
     }
 
-    fn test_eval(input: String) -> Rc<object::Object> {
-        let mut evaluator = Evaluator::new();
-        let l = lexer::Lexer::new(&input);
-        let mut p = parser::Parser::new(l);
-        let program = p.parse_program();
+    thread_local! {
+        static RECORDED: RefCell<Vec<i64>> = RefCell::new(Vec::new());
+    }
+
+    fn record(args: Vec<Rc<object::Object>>, _: &mut Evaluator) -> Signal {
+        if let object::Object::Integer(value) = *args[0] {
+            RECORDED.with(|recorded| recorded.borrow_mut().push(value));
+        }
+        Signal::Value(Rc::clone(&args[0]))
+    }
 
-        if p.errors.len() != 0 {
+    #[test]
+    fn test_evaluation_order() {
+        counted_array!(
+            let tests: [(&str, Vec<i64>); _] = [
+                ("record(1) + record(2)", vec![1, 2]),
+                ("record(1) * record(2) - record(3)", vec![1, 2, 3]),
+                ("record(1) - record(2) * record(3)", vec![1, 2, 3]),
+                ("record(1) < record(2) == record(3) > record(4)", vec![1, 2, 3, 4]),
+                ("record(1) ** record(2) ** record(3)", vec![1, 2, 3]),
+                ("record(1) && record(2) || record(3)", vec![1, 2]),
+                ("let f = fn(a, b, c) { a }; f(record(1), record(2), record(3))", vec![1, 2, 3]),
+                ("let f = fn(a, b) { a }; f(record(1) + record(2), f(record(3), record(4)))", vec![1, 2, 3, 4]),
+                ("{ record(1): record(2), record(3): record(4) }", vec![1, 2, 3, 4]),
+                ("[record(1), record(2)][record(0)]", vec![1, 2, 0]),
+                ("let id = fn(x) { x }; [id][record(0)](record(5))", vec![0, 5]),
+                ("for (x in [3, 1, 2]) { record(x) }", vec![3, 1, 2]),
+                ("for (i, x in [5, 6]) { record(i); record(x) }", vec![0, 5, 1, 6]),
+                ("for (k in {7: 8}) { record(k) }", vec![7]),
+                ("for (k, v in {7: 8}) { record(k); record(v) }", vec![7, 8]),
+                ("for (k in {30: 0, 10: 0, 20: 0}) { record(k) }", vec![10, 20, 30]),
+                ("for (k, v in {\"b\": 1, true: 2, 5: 3, \"a\": 4, false: 5}) { record(v) }", vec![3, 5, 2, 4, 1]),
+                ("for (i in range(3)) { record(i) }", vec![0, 1, 2]),
+                ("let a = [0, 0]; a[record(1)] += record(2); a[record(1)]", vec![1, 2, 1]),
+                ("for (i in range(2, 4)) { record(i) }", vec![2, 3]),
+                ("for (i, c in \"añb\") { record(i); record(len(c)) }", vec![0, 1, 1, 2, 2, 1]),
+                ("for (i in range(1, 10)) { if (i % 2 == 0) { continue; } if (i > 5) { break; } record(i) }", vec![1, 3, 5]),
+            ]
+        );
+
+        for t in tests {
+            RECORDED.with(|recorded| recorded.borrow_mut().clear());
+
+            let mut evaluator = Evaluator::new();
+            evaluator.builtin.insert(
+                "record".to_string(),
+                object::Object::new_builtin(object::BuiltinFunc(100, record)),
+            );
+            let l = lexer::Lexer::new(t.0);
+            let mut p = parser::Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(&p);
+            evaluator.eval_program(program);
+
+            let recorded = RECORDED.with(|recorded| recorded.borrow().clone());
+            assert_eq!(recorded, t.1, "{}", t.0);
+        }
+    }
+
+    fn check_parser_errors(p: &parser::Parser<'_>) {
+        if !p.errors.is_empty() {
             let mut s = "".to_string();
-            for err in p.errors {
+            for err in &p.errors {
                 s += &format!("\t{}\r\n", err);
             }
             panic!("parser errors:\r\n{}", s);
         }
+    }
+
+    fn test_eval(input: String) -> Rc<object::Object> {
+        let mut evaluator = Evaluator::new();
+        let l = lexer::Lexer::new(&input);
+        let mut p = parser::Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(&p);
 
         match evaluator.eval_program(program) {
-            Some(obj) => return Rc::clone(&obj),
-            None => panic!(),
+            Signal::Value(obj) | Signal::Throw(obj) => return obj,
+            Signal::Exit(code) => panic!("exited with {}", code),
+            _ => panic!(),
         }
     }
 
@@ -1,153 +1,415 @@
 use super::token;
+use std::borrow::Cow;
+use std::rc::Rc;
 
-pub struct Lexer {
-    pub input: String,
-    position: usize,
+pub struct Lexer<'a> {
+    pub input: &'a str,
+    pub errors: Vec<(token::Span, String)>,
+    // Set when a string or block comment is still open at the end of input.
+    pub unterminated: bool,
+    file: Option<Rc<str>>,
     read_position: usize,
     ch: char,
+    location: token::Position,
 }
 
 #[allow(dead_code)]
-impl Lexer {
-    pub fn new(input: &str) -> Lexer {
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Lexer<'a> {
         let mut l = Lexer {
-            input: input.to_string(),
-            position: 0,
+            input,
+            errors: Vec::new(),
+            unterminated: false,
+            file: None,
             read_position: 0,
-            ch: 'a',
+            ch: '\0',
+            location: token::Position {
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
         };
         l.read_char();
         return l;
     }
 
+    pub fn new_with_file(input: &'a str, file: &str) -> Lexer<'a> {
+        let mut l = Lexer::new(input);
+        l.file = Some(Rc::from(file));
+        l
+    }
+
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = '\0';
-        } else {
-            self.ch = self.input.chars().nth(self.read_position).unwrap();
+        // `ch` is a real character (not the start or EOF sentinel) only when it
+        // has already been consumed from the input.
+        if self.read_position > self.location.offset {
+            if self.ch == '\n' {
+                self.location.line += 1;
+                self.location.column = 1;
+            } else {
+                self.location.column += 1;
+            }
+            self.location.offset = self.read_position;
+        }
+
+        match self.input[self.read_position..].chars().next() {
+            Some(ch) => {
+                self.ch = ch;
+                self.read_position += ch.len_utf8();
+            }
+            None => self.ch = '\0',
         }
-        self.position = self.read_position;
-        self.read_position += 1;
     }
 
-    pub fn next_token(&mut self) -> token::Token {
+    pub fn next_token(&mut self) -> token::Token<'a> {
         self.skip_whitespace();
 
-        let tok = match self.ch {
-            '=' => {
+        let start = self.location;
+        let token_type = match self.ch {
+            '=' => match self.peek_char() {
+                '=' => {
+                    self.read_char();
+                    token::TokenType::EQ
+                }
+                '>' => {
+                    self.read_char();
+                    token::TokenType::ARROW
+                }
+                _ => token::TokenType::ASSIGN,
+            },
+            '+' => {
                 if self.peek_char() == '=' {
-                    let ch = self.ch;
                     self.read_char();
-                    token::new_token(token::TokenType::EQ, ch.to_string() + &self.ch.to_string())
+                    token::TokenType::PLUSASSIGN
                 } else {
-                    token::new_token(token::TokenType::ASSIGN, self.ch.to_string())
+                    token::TokenType::PLUS
+                }
+            }
+            '-' => {
+                if self.peek_char() == '=' {
+                    self.read_char();
+                    token::TokenType::MINUSASSIGN
+                } else {
+                    token::TokenType::MINUS
                 }
             }
-            '+' => token::new_token(token::TokenType::PLUS, self.ch.to_string()),
-            '-' => token::new_token(token::TokenType::MINUS, self.ch.to_string()),
             '!' => {
                 if self.peek_char() == '=' {
-                    let ch = self.ch;
                     self.read_char();
-                    token::new_token(
-                        token::TokenType::NOTEQ,
-                        ch.to_string() + &self.ch.to_string(),
-                    )
+                    token::TokenType::NOTEQ
+                } else {
+                    token::TokenType::BANG
+                }
+            }
+            '*' => match self.peek_char() {
+                '*' => {
+                    self.read_char();
+                    token::TokenType::POWER
+                }
+                '=' => {
+                    self.read_char();
+                    token::TokenType::ASTERISKASSIGN
+                }
+                _ => token::TokenType::ASTERISK,
+            },
+            '/' => {
+                if self.peek_char() == '=' {
+                    self.read_char();
+                    token::TokenType::SLASHASSIGN
+                } else {
+                    token::TokenType::SLASH
+                }
+            }
+            '%' => {
+                if self.peek_char() == '=' {
+                    self.read_char();
+                    token::TokenType::PERCENTASSIGN
                 } else {
-                    token::new_token(token::TokenType::BANG, self.ch.to_string())
+                    token::TokenType::PERCENT
                 }
             }
-            '*' => token::new_token(token::TokenType::ASTERISK, self.ch.to_string()),
-            '/' => token::new_token(token::TokenType::SLASH, self.ch.to_string()),
-            '<' => token::new_token(token::TokenType::LT, self.ch.to_string()),
-            '>' => token::new_token(token::TokenType::GT, self.ch.to_string()),
-            ',' => token::new_token(token::TokenType::COMMA, self.ch.to_string()),
-            ';' => token::new_token(token::TokenType::SEMICOLON, self.ch.to_string()),
-            '(' => token::new_token(token::TokenType::LPAREN, self.ch.to_string()),
-            ')' => token::new_token(token::TokenType::RPAREN, self.ch.to_string()),
-            '{' => token::new_token(token::TokenType::LBRACE, self.ch.to_string()),
-            '}' => token::new_token(token::TokenType::RBRACE, self.ch.to_string()),
-            '[' => token::new_token(token::TokenType::LBRACKET, self.ch.to_string()),
-            ']' => token::new_token(token::TokenType::RBRACKET, self.ch.to_string()),
-            '"' => token::new_token(token::TokenType::STRING, self.read_string()),
-            ':' => token::new_token(token::TokenType::COLON, self.ch.to_string()),
-            '.' => token::new_token(token::TokenType::DOT, self.ch.to_string()),
-            '\0' => token::Token {
-                token_type: token::TokenType::EOF,
-                literal: String::from(""),
+            '<' => match self.peek_char() {
+                '=' => {
+                    self.read_char();
+                    token::TokenType::LTEQ
+                }
+                '<' => {
+                    self.read_char();
+                    token::TokenType::LSHIFT
+                }
+                _ => token::TokenType::LT,
+            },
+            '>' => match self.peek_char() {
+                '=' => {
+                    self.read_char();
+                    token::TokenType::GTEQ
+                }
+                '>' => {
+                    self.read_char();
+                    token::TokenType::RSHIFT
+                }
+                _ => token::TokenType::GT,
             },
+            '&' => {
+                if self.peek_char() == '&' {
+                    self.read_char();
+                    token::TokenType::AND
+                } else {
+                    token::TokenType::AMPERSAND
+                }
+            }
+            '|' => {
+                if self.peek_char() == '|' {
+                    self.read_char();
+                    token::TokenType::OR
+                } else {
+                    token::TokenType::PIPE
+                }
+            }
+            '^' => token::TokenType::CARET,
+            ',' => token::TokenType::COMMA,
+            ';' => token::TokenType::SEMICOLON,
+            '(' => token::TokenType::LPAREN,
+            ')' => token::TokenType::RPAREN,
+            '{' => token::TokenType::LBRACE,
+            '}' => token::TokenType::RBRACE,
+            '[' => token::TokenType::LBRACKET,
+            ']' => token::TokenType::RBRACKET,
+            '"' => {
+                let literal = self.read_string();
+                self.read_char();
+                return token::new_token(token::TokenType::STRING, literal, self.span_from(start));
+            }
+            ':' => token::TokenType::COLON,
+            '.' => {
+                if self.peek_char() == '.' && self.peek_char_nth(1) == '.' {
+                    self.read_char();
+                    self.read_char();
+                    token::TokenType::ELLIPSIS
+                } else {
+                    token::TokenType::DOT
+                }
+            }
+            '\0' => token::TokenType::EOF,
             _ => {
-                if self.ch.is_alphabetic() {
-                    let literal = self.read_identifier();
-                    let token_type = token::lookup_ident(&literal);
+                if is_identifier_start(self.ch) {
+                    self.read_identifier();
+                    let token_type = token::lookup_ident(self.slice_from(start));
 
-                    return token::new_token(token_type, literal);
-                } else if self.ch.is_numeric() {
-                    return token::new_token(token::TokenType::INT, self.read_number());
+                    return self.new_token(start, token_type);
+                } else if self.ch.is_ascii_digit() {
+                    let token_type = self.read_number();
+                    return self.new_token(start, token_type);
                 } else {
-                    token::new_token(token::TokenType::ILLEGAL, self.ch.to_string())
+                    token::TokenType::ILLEGAL
                 }
             }
         };
 
         self.read_char();
-        return tok;
+        self.new_token(start, token_type)
+    }
+
+    fn new_token(&self, start: token::Position, token_type: token::TokenType) -> token::Token<'a> {
+        let literal = Cow::Borrowed(self.slice_from(start));
+        token::new_token(token_type, literal, self.span_from(start))
+    }
+
+    fn span_from(&self, start: token::Position) -> token::Span {
+        token::Span {
+            file: self.file.clone(),
+            start,
+            end: self.location,
+        }
+    }
+
+    fn slice_from(&self, start: token::Position) -> &'a str {
+        &self.input[start.offset..self.location.offset]
     }
 
-    fn read_number(&mut self) -> String {
-        let position = self.position;
-        while self.ch.is_numeric() {
+    fn read_number(&mut self) -> token::TokenType {
+        let mut token_type = token::TokenType::INT;
+        self.read_digits();
+
+        if self.ch == '.' && self.peek_char().is_ascii_digit() {
+            token_type = token::TokenType::FLOAT;
             self.read_char();
+            self.read_digits();
+        }
+
+        if self.ch == 'e' || self.ch == 'E' {
+            let sign = self.peek_char() == '+' || self.peek_char() == '-';
+            let digit = if sign {
+                self.peek_char_nth(1)
+            } else {
+                self.peek_char()
+            };
+            if digit.is_ascii_digit() {
+                token_type = token::TokenType::FLOAT;
+                self.read_char();
+                if sign {
+                    self.read_char();
+                }
+                self.read_digits();
+            }
         }
 
-        return self.get_slice(position, self.position);
+        token_type
     }
 
-    fn read_identifier(&mut self) -> String {
-        let position = self.position;
-        while self.ch.is_alphabetic() {
+    fn read_digits(&mut self) {
+        while self.ch.is_ascii_digit() {
             self.read_char();
         }
+    }
 
-        return self.get_slice(position, self.position);
+    fn read_identifier(&mut self) {
+        while is_identifier_char(self.ch) {
+            self.read_char();
+        }
     }
 
-    fn read_string(&mut self) -> String {
-        let position = self.position + 1;
+    fn read_string(&mut self) -> Cow<'a, str> {
+        let start = self.location;
+        let content_start = self.read_position;
+        // Only allocated once an escape sequence forces the value to differ
+        // from the source text.
+        let mut decoded: Option<String> = None;
         loop {
             self.read_char();
-            if self.ch == '"' || self.ch == '\0' {
-                break;
+            match self.ch {
+                '"' => break,
+                '\0' => {
+                    let span = self.span_from(start);
+                    self.errors.push((span, "unterminated string".to_string()));
+                    self.unterminated = true;
+                    break;
+                }
+                '\\' => {
+                    let escape_start = self.location;
+                    let mut value = decoded.take().unwrap_or_else(|| {
+                        self.input[content_start..escape_start.offset].to_string()
+                    });
+                    self.read_char();
+                    match self.ch {
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        'r' => value.push('\r'),
+                        '0' => value.push('\0'),
+                        '\\' => value.push('\\'),
+                        '"' => value.push('"'),
+                        'u' => match self.read_unicode_escape() {
+                            Some(c) => value.push(c),
+                            None => {
+                                let span = self.span_from(escape_start);
+                                self.errors
+                                    .push((span, "invalid unicode escape".to_string()));
+                            }
+                        },
+                        // reported as an unterminated string on the next iteration
+                        '\0' => {}
+                        c => {
+                            let span = self.span_from(escape_start);
+                            self.errors
+                                .push((span, format!("unknown escape sequence \\{}", c)));
+                            value.push(c);
+                        }
+                    }
+                    decoded = Some(value);
+                }
+                c => {
+                    if let Some(value) = decoded.as_mut() {
+                        value.push(c);
+                    }
+                }
             }
         }
 
-        return self.get_slice(position, self.position);
+        match decoded {
+            Some(value) => Cow::Owned(value),
+            None => Cow::Borrowed(&self.input[content_start..self.location.offset]),
+        }
     }
 
-    fn get_slice(&self, start: usize, end: usize) -> String {
-        let begin = self.input.char_indices().nth(start).unwrap().0;
-        if let Some(end) = self.input.char_indices().nth(end) {
-            let str2 = &self.input[begin..end.0];
-            return String::from(str2);
+    fn read_unicode_escape(&mut self) -> Option<char> {
+        if self.peek_char() != '{' {
+            return None;
         }
+        self.read_char();
 
-        let str2 = &self.input[begin..];
-        return String::from(str2);
+        let hex_start = self.read_position;
+        while self.peek_char().is_ascii_hexdigit() {
+            self.read_char();
+        }
+        let hex = &self.input[hex_start..self.read_position];
+        if self.peek_char() != '}' || hex.len() > 6 {
+            return None;
+        }
+        self.read_char();
+
+        u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
     }
 
     fn skip_whitespace(&mut self) {
-        while self.ch == ' ' || self.ch == '\t' || self.ch == '\n' || self.ch == '\r' {
+        loop {
+            match self.ch {
+                ' ' | '\t' | '\n' | '\r' => self.read_char(),
+                '/' if self.peek_char() == '/' => self.skip_line_comment(),
+                '/' if self.peek_char() == '*' => self.skip_block_comment(),
+                _ => break,
+            }
+        }
+    }
+
+    fn skip_line_comment(&mut self) {
+        while self.ch != '\n' && self.ch != '\0' {
             self.read_char();
         }
     }
 
-    fn peek_char(&mut self) -> char {
-        if self.read_position >= self.input.len() {
-            return '\0';
-        } else {
-            return self.input.chars().nth(self.read_position).unwrap();
+    fn skip_block_comment(&mut self) {
+        let start = self.location;
+        let mut depth = 0;
+        loop {
+            if self.ch == '/' && self.peek_char() == '*' {
+                depth += 1;
+                self.read_char();
+            } else if self.ch == '*' && self.peek_char() == '/' {
+                depth -= 1;
+                self.read_char();
+                if depth == 0 {
+                    self.read_char();
+                    return;
+                }
+            } else if self.ch == '\0' {
+                let span = self.span_from(start);
+                self.errors
+                    .push((span, "unterminated block comment".to_string()));
+                self.unterminated = true;
+                return;
+            }
+            self.read_char();
         }
     }
+
+    fn peek_char(&self) -> char {
+        self.peek_char_nth(0)
+    }
+
+    fn peek_char_nth(&self, n: usize) -> char {
+        self.input[self.read_position..]
+            .chars()
+            .nth(n)
+            .unwrap_or('\0')
+    }
+}
+
+fn is_identifier_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+fn is_identifier_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
 }
 
 #[cfg(test)]
@@ -193,7 +455,7 @@ let add = fn(x, y) {
 
 let result = add(five, ten);
 
-!-/*5;
+!-/ *5;
 5 < 10 > 5;
 if (5 < 10) {
     return true;
@@ -309,4 +571,296 @@ if (5 < 10) {
             assert_eq!(tok.literal, String::from(literal));
         }
     }
+
+    #[test]
+    fn test_operator_tokens() {
+        let input = "<= >= < > % ** * && || & | ^ << >> == != = += -= *= /= %= ... .. => -1";
+        counted_array!(
+            let tests: [(token::TokenType, &str); _] = [
+                (token::TokenType::LTEQ, "<="),
+                (token::TokenType::GTEQ, ">="),
+                (token::TokenType::LT, "<"),
+                (token::TokenType::GT, ">"),
+                (token::TokenType::PERCENT, "%"),
+                (token::TokenType::POWER, "**"),
+                (token::TokenType::ASTERISK, "*"),
+                (token::TokenType::AND, "&&"),
+                (token::TokenType::OR, "||"),
+                (token::TokenType::AMPERSAND, "&"),
+                (token::TokenType::PIPE, "|"),
+                (token::TokenType::CARET, "^"),
+                (token::TokenType::LSHIFT, "<<"),
+                (token::TokenType::RSHIFT, ">>"),
+                (token::TokenType::EQ, "=="),
+                (token::TokenType::NOTEQ, "!="),
+                (token::TokenType::ASSIGN, "="),
+                (token::TokenType::PLUSASSIGN, "+="),
+                (token::TokenType::MINUSASSIGN, "-="),
+                (token::TokenType::ASTERISKASSIGN, "*="),
+                (token::TokenType::SLASHASSIGN, "/="),
+                (token::TokenType::PERCENTASSIGN, "%="),
+                (token::TokenType::ELLIPSIS, "..."),
+                (token::TokenType::DOT, "."),
+                (token::TokenType::DOT, "."),
+                (token::TokenType::ARROW, "=>"),
+                (token::TokenType::MINUS, "-"),
+                (token::TokenType::INT, "1"),
+                (token::TokenType::EOF, ""),
+            ]
+        );
+
+        let mut l = Lexer::new(input);
+
+        for (token_type, literal) in tests {
+            let tok = l.next_token();
+
+            assert_eq!(tok.token_type, token_type);
+            assert_eq!(tok.literal, String::from(literal));
+        }
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let input = "user_id row2 _tmp _ a_1_b 2abc letter iffy return_value try catch finally throw thrower";
+        counted_array!(
+            let tests: [(token::TokenType, &str); _] = [
+                (token::TokenType::IDENT, "user_id"),
+                (token::TokenType::IDENT, "row2"),
+                (token::TokenType::IDENT, "_tmp"),
+                (token::TokenType::IDENT, "_"),
+                (token::TokenType::IDENT, "a_1_b"),
+                (token::TokenType::INT, "2"),
+                (token::TokenType::IDENT, "abc"),
+                (token::TokenType::IDENT, "letter"),
+                (token::TokenType::IDENT, "iffy"),
+                (token::TokenType::IDENT, "return_value"),
+                (token::TokenType::TRY, "try"),
+                (token::TokenType::CATCH, "catch"),
+                (token::TokenType::FINALLY, "finally"),
+                (token::TokenType::THROW, "throw"),
+                (token::TokenType::IDENT, "thrower"),
+                (token::TokenType::EOF, ""),
+            ]
+        );
+
+        let mut l = Lexer::new(input);
+
+        for (token_type, literal) in tests {
+            let tok = l.next_token();
+
+            assert_eq!(tok.token_type, token_type);
+            assert_eq!(tok.literal, String::from(literal));
+        }
+    }
+
+    #[test]
+    fn test_number_tokens() {
+        let input = "5 1.05 1.0 10.25 1e-3 2.5E+2 3e8 1.x 4e";
+        counted_array!(
+            let tests: [(token::TokenType, &str); _] = [
+                (token::TokenType::INT, "5"),
+                (token::TokenType::FLOAT, "1.05"),
+                (token::TokenType::FLOAT, "1.0"),
+                (token::TokenType::FLOAT, "10.25"),
+                (token::TokenType::FLOAT, "1e-3"),
+                (token::TokenType::FLOAT, "2.5E+2"),
+                (token::TokenType::FLOAT, "3e8"),
+                (token::TokenType::INT, "1"),
+                (token::TokenType::DOT, "."),
+                (token::TokenType::IDENT, "x"),
+                (token::TokenType::INT, "4"),
+                (token::TokenType::IDENT, "e"),
+                (token::TokenType::EOF, ""),
+            ]
+        );
+
+        let mut l = Lexer::new(input);
+
+        for (token_type, literal) in tests {
+            let tok = l.next_token();
+
+            assert_eq!(tok.token_type, token_type);
+            assert_eq!(tok.literal, String::from(literal));
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "
+// a line comment
+let x = 5; // trailing comment
+/* a block
+   comment */
+x / 2;
+/* outer /* nested */ still outer */ x * 1;
+//";
+        counted_array!(
+            let tests: [(token::TokenType, &str); _] = [
+                (token::TokenType::LET, "let"),
+                (token::TokenType::IDENT, "x"),
+                (token::TokenType::ASSIGN, "="),
+                (token::TokenType::INT, "5"),
+                (token::TokenType::SEMICOLON, ";"),
+                (token::TokenType::IDENT, "x"),
+                (token::TokenType::SLASH, "/"),
+                (token::TokenType::INT, "2"),
+                (token::TokenType::SEMICOLON, ";"),
+                (token::TokenType::IDENT, "x"),
+                (token::TokenType::ASTERISK, "*"),
+                (token::TokenType::INT, "1"),
+                (token::TokenType::SEMICOLON, ";"),
+                (token::TokenType::EOF, ""),
+            ]
+        );
+
+        let mut l = Lexer::new(input);
+
+        for (token_type, literal) in tests {
+            let tok = l.next_token();
+
+            assert_eq!(tok.token_type, token_type);
+            assert_eq!(tok.literal, String::from(literal));
+        }
+        assert!(l.errors.is_empty());
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let input = "let x = 5;\n/* open /* nested */\nlet y = 6;";
+
+        let mut l = Lexer::new(input);
+        for _ in 0..5 {
+            l.next_token();
+        }
+        let tok = l.next_token();
+
+        assert_eq!(tok.token_type, token::TokenType::EOF);
+        assert_eq!(error_messages(&l), vec!["2:1: unterminated block comment"]);
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let input = r#""a\nb" "tab\there" "back\\slash" "say \"hi\"" "\u{3042}\u{1F600}" "\r\0""#;
+        counted_array!(
+            let tests: [&str; _] = [
+                "a\nb",
+                "tab\there",
+                "back\\slash",
+                "say \"hi\"",
+                "\u{3042}\u{1F600}",
+                "\r\0",
+            ]
+        );
+
+        let mut l = Lexer::new(input);
+
+        for literal in tests {
+            let tok = l.next_token();
+
+            assert_eq!(tok.token_type, token::TokenType::STRING);
+            assert_eq!(tok.literal, String::from(literal));
+        }
+        assert_eq!(l.next_token().token_type, token::TokenType::EOF);
+        assert!(l.errors.is_empty());
+    }
+
+    #[test]
+    fn test_string_errors() {
+        counted_array!(
+            let tests: [(&str, &str, &str); _] = [
+                ("let s = \"abc;\nlet t = 1;", "abc;\nlet t = 1;", "1:9: unterminated string"),
+                ("\"abc\\", "abc", "1:1: unterminated string"),
+                ("\"a\\qb\"", "aqb", "1:3: unknown escape sequence \\q"),
+                ("\"\\u{zz}\"", "zz}", "1:2: invalid unicode escape"),
+                ("\"\\u{110000}\"", "", "1:2: invalid unicode escape"),
+            ]
+        );
+
+        for (input, literal, error) in tests {
+            let mut l = Lexer::new(input);
+            let mut tok = l.next_token();
+            while tok.token_type != token::TokenType::STRING {
+                tok = l.next_token();
+            }
+
+            assert_eq!(tok.literal, String::from(literal));
+            assert_eq!(error_messages(&l), vec![error]);
+        }
+    }
+
+    #[test]
+    fn test_multibyte_input() {
+        let input = "let 名前 = \"日本語\";\n名前 + \"\\u{3042}\"";
+        counted_array!(
+            let tests: [(token::TokenType, &str, usize, usize, usize); _] = [
+                (token::TokenType::LET, "let", 1, 1, 0),
+                (token::TokenType::IDENT, "名前", 1, 5, 4),
+                (token::TokenType::ASSIGN, "=", 1, 8, 11),
+                (token::TokenType::STRING, "日本語", 1, 10, 13),
+                (token::TokenType::SEMICOLON, ";", 1, 15, 24),
+                (token::TokenType::IDENT, "名前", 2, 1, 26),
+                (token::TokenType::PLUS, "+", 2, 4, 33),
+                (token::TokenType::STRING, "あ", 2, 6, 35),
+                (token::TokenType::EOF, "", 2, 16, 45),
+            ]
+        );
+
+        let mut l = Lexer::new(input);
+
+        for (token_type, literal, line, column, offset) in tests {
+            let tok = l.next_token();
+
+            assert_eq!(tok.token_type, token_type);
+            assert_eq!(tok.literal, String::from(literal));
+            assert_eq!(tok.span.start.line, line);
+            assert_eq!(tok.span.start.column, column);
+            assert_eq!(tok.span.start.offset, offset);
+            if token_type == token::TokenType::IDENT {
+                assert!(matches!(tok.literal, Cow::Borrowed(_)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let input = "let x = 5;\n  foo(\"bar\");";
+        counted_array!(
+            let tests: [(token::TokenType, usize, usize, usize, usize); _] = [
+                (token::TokenType::LET, 1, 1, 0, 3),
+                (token::TokenType::IDENT, 1, 5, 4, 5),
+                (token::TokenType::ASSIGN, 1, 7, 6, 7),
+                (token::TokenType::INT, 1, 9, 8, 9),
+                (token::TokenType::SEMICOLON, 1, 10, 9, 10),
+                (token::TokenType::IDENT, 2, 3, 13, 16),
+                (token::TokenType::LPAREN, 2, 6, 16, 17),
+                (token::TokenType::STRING, 2, 7, 17, 22),
+                (token::TokenType::RPAREN, 2, 12, 22, 23),
+                (token::TokenType::SEMICOLON, 2, 13, 23, 24),
+                (token::TokenType::EOF, 2, 14, 24, 24),
+            ]
+        );
+
+        let mut l = Lexer::new_with_file(input, "main.str");
+
+        for (token_type, line, column, start, end) in tests {
+            let tok = l.next_token();
+
+            assert_eq!(tok.token_type, token_type);
+            assert_eq!(tok.span.start.line, line);
+            assert_eq!(tok.span.start.column, column);
+            assert_eq!(tok.span.start.offset, start);
+            assert_eq!(tok.span.end.offset, end);
+            assert_eq!(
+                format!("{}", tok.span),
+                format!("main.str:{}:{}", line, column)
+            );
+        }
+    }
+
+    fn error_messages(l: &Lexer<'_>) -> Vec<String> {
+        l.errors
+            .iter()
+            .map(|(span, message)| format!("{}: {}", span, message))
+            .collect()
+    }
 }
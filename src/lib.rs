@@ -6,10 +6,12 @@ extern crate counted_array;
 
 pub mod ast;
 pub mod builtin;
+pub mod diagnostics;
 pub mod environment;
 pub mod evaluator;
 pub mod lexer;
 pub mod object;
 pub mod parser;
 pub mod repl;
+pub mod runner;
 pub mod token;
@@ -6,15 +6,39 @@ extern crate counted_array;
 
 mod ast;
 mod builtin;
+mod diagnostics;
 mod environment;
 mod evaluator;
 mod lexer;
 mod object;
 mod parser;
 mod repl;
+mod runner;
 mod token;
 
+use std::{env, process, thread};
+
 fn main() {
-    let mut r = repl::Repl::new();
-    r.start();
+    // https://no-color.org
+    let mut colour = env::var_os("NO_COLOR").is_none();
+    let mut file = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--no-color" => colour = false,
+            _ => file = Some(arg),
+        }
+    }
+
+    let renderer = diagnostics::Renderer::new(colour);
+    let run = thread::Builder::new()
+        .stack_size(evaluator::STACK_SIZE)
+        .spawn(move || match file {
+            Some(path) => runner::run_file(&path, &renderer),
+            None => {
+                let mut r = repl::Repl::new(renderer);
+                r.start()
+            }
+        })
+        .unwrap();
+    process::exit(run.join().unwrap());
 }
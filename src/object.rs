@@ -1,4 +1,4 @@
-use super::{ast, environment, evaluator};
+use super::{ast, environment, evaluator, token};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
@@ -8,7 +8,7 @@ use std::rc::Rc;
 #[derive(Clone)]
 pub struct BuiltinFunc(
     pub i64,
-    pub fn(Vec<Rc<Object>>, &mut evaluator::Evaluator) -> Rc<Object>,
+    pub fn(Vec<Rc<Object>>, &mut evaluator::Evaluator) -> evaluator::Signal,
 );
 impl PartialEq for BuiltinFunc {
     fn eq(&self, other: &Self) -> bool {
@@ -22,18 +22,30 @@ pub enum Object {
     Float(f64),
     String(String),
     Boolean(bool),
-    Return(Rc<Object>),
-    Error(String),
+    Error {
+        message: String,
+        // One of the `*_ERROR` kinds below, or whatever a script threw.
+        kind: String,
+        span: Option<token::Span>,
+        // The calls the error unwound through, innermost first: the name of
+        // each function and where it was called from.
+        trace: Vec<(String, token::Span)>,
+    },
     Builtin(BuiltinFunc),
     Array(Vec<Rc<Object>>),
     Hash(HashMap<Rc<Object>, Rc<Object>>),
+    Range {
+        start: i64,
+        end: i64,
+    },
     Function {
-        parameters: Vec<ast::Expression>,
-        body: ast::Statement,
+        // Set for functions made by `fn name(..) {..}`.
+        name: Option<String>,
+        parameters: Vec<ast::Parameter>,
+        body: Box<ast::Statement>,
         env: Rc<RefCell<environment::Environment>>,
     },
     Null,
-    Exit,
 }
 
 impl fmt::Display for Object {
@@ -43,25 +55,25 @@ impl fmt::Display for Object {
             Object::Float(_) => return write!(f, "FLOAT"),
             Object::String(_) => return write!(f, "STRING"),
             Object::Boolean(_) => return write!(f, "BOOLEAN"),
-            Object::Return(_) => return write!(f, "RETURN"),
-            Object::Error(_) => return write!(f, "ERROR"),
+            Object::Error { .. } => return write!(f, "ERROR"),
             Object::Builtin(_) => return write!(f, "BUILTIN"),
             Object::Array(_) => return write!(f, "ARRAY"),
             Object::Hash(_) => return write!(f, "HASH"),
-            Object::Function {
-                parameters: _,
-                body: _,
-                env: _,
-            } => return write!(f, "FUNCTION"),
+            Object::Range { .. } => write!(f, "RANGE"),
+            Object::Function { .. } => return write!(f, "FUNCTION"),
             Object::Null => return write!(f, "NULL"),
-            Object::Exit => return write!(f, "Exit"),
         }
     }
 }
 
 impl Object {
-    pub fn new_error(message: String) -> Rc<Self> {
-        return Rc::new(Self::Error(message));
+    pub fn new_error(kind: &str, message: String) -> Rc<Self> {
+        return Rc::new(Self::Error {
+            message,
+            kind: kind.to_string(),
+            span: None,
+            trace: Vec::new(),
+        });
     }
     pub fn new_builtin(func: BuiltinFunc) -> Rc<Self> {
         return Rc::new(Self::Builtin(func));
@@ -70,11 +82,13 @@ impl Object {
     pub fn string(&self) -> String {
         match self {
             Object::Integer(value) => return format!("{}", value),
-            Object::Float(value) => return format!("{}", value),
-            Object::String(value) => return format!("\"{}\"", value),
+            Object::Float(value) => format!("{:?}", value),
+            Object::String(value) => format!("{:?}", value),
             Object::Boolean(value) => return format!("{}", value),
-            Object::Return(value) => return format!("{}", value),
-            Object::Error(value) => return format!("{}", value),
+            Object::Error { message, span, .. } => match span {
+                Some(span) => format!("{}: {}", span, message),
+                None => message.clone(),
+            },
             Object::Builtin(_) => return format!("builtin-functions"),
             Object::Array(array) => {
                 let mut s = "[".to_string();
@@ -96,7 +110,9 @@ impl Object {
                 s += " }";
                 return s;
             }
+            Object::Range { start, end } => format!("range({}, {})", start, end),
             Object::Function {
+                name,
                 parameters,
                 body,
                 env: _,
@@ -110,10 +126,9 @@ impl Object {
                     }
                 }
 
-                return format!("fn({}) {}", s, body);
+                return format!("fn {}({}) {}", name.as_deref().unwrap_or(""), s, body);
             }
             Object::Null => return "NULL".to_string(),
-            Object::Exit => return "Exit".to_string(),
         }
     }
 }
@@ -134,4 +149,14 @@ impl Hash for Object {
 pub const TRUE: Object = Object::Boolean(true);
 pub const FALSE: Object = Object::Boolean(false);
 pub const NULL: Object = Object::Null;
-pub const EXIT: Object = Object::Exit;
+
+// Kinds of the errors raised by the interpreter itself.
+pub const ERROR: &str = "Error";
+pub const TYPE_ERROR: &str = "TypeError";
+pub const NAME_ERROR: &str = "NameError";
+pub const INDEX_ERROR: &str = "IndexError";
+pub const ARGUMENT_ERROR: &str = "ArgumentError";
+pub const ARITHMETIC_ERROR: &str = "ArithmeticError";
+pub const MATCH_ERROR: &str = "MatchError";
+pub const IMPORT_ERROR: &str = "ImportError";
+pub const RECURSION_ERROR: &str = "RecursionError";
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+use std::fmt;
 use std::rc::Rc;
 
 use super::{ast, lexer, token};
@@ -7,11 +9,18 @@ use super::{ast, lexer, token};
 pub enum Precedence {
     LOWEST,
     ASSIGN,
+    OR,
+    AND,
     EQUALS,
     LESSGREATER,
+    BITOR,
+    BITXOR,
+    BITAND,
+    SHIFT,
     SUM,
     PRODUCT,
     PREFIX,
+    POWER,
     DOT,
     CALL,
     INDEX,
@@ -21,43 +30,177 @@ pub enum Precedence {
 pub fn token_type_to_precedence(t: &token::TokenType) -> Precedence {
     match t {
         token::TokenType::ASSIGN => return Precedence::ASSIGN,
+        token::TokenType::PLUSASSIGN => return Precedence::ASSIGN,
+        token::TokenType::MINUSASSIGN => return Precedence::ASSIGN,
+        token::TokenType::ASTERISKASSIGN => return Precedence::ASSIGN,
+        token::TokenType::SLASHASSIGN => return Precedence::ASSIGN,
+        token::TokenType::PERCENTASSIGN => return Precedence::ASSIGN,
+        token::TokenType::OR => return Precedence::OR,
+        token::TokenType::AND => return Precedence::AND,
         token::TokenType::EQ => return Precedence::EQUALS,
         token::TokenType::NOTEQ => return Precedence::EQUALS,
         token::TokenType::LT => return Precedence::LESSGREATER,
         token::TokenType::GT => return Precedence::LESSGREATER,
+        token::TokenType::LTEQ => return Precedence::LESSGREATER,
+        token::TokenType::GTEQ => return Precedence::LESSGREATER,
+        token::TokenType::PIPE => return Precedence::BITOR,
+        token::TokenType::CARET => return Precedence::BITXOR,
+        token::TokenType::AMPERSAND => return Precedence::BITAND,
+        token::TokenType::LSHIFT => return Precedence::SHIFT,
+        token::TokenType::RSHIFT => return Precedence::SHIFT,
         token::TokenType::PLUS => return Precedence::SUM,
         token::TokenType::MINUS => return Precedence::SUM,
         token::TokenType::SLASH => return Precedence::PRODUCT,
         token::TokenType::ASTERISK => return Precedence::PRODUCT,
-        token::TokenType::DOT => return Precedence::DOT,
+        token::TokenType::PERCENT => return Precedence::PRODUCT,
+        token::TokenType::POWER => return Precedence::POWER,
         token::TokenType::LPAREN => return Precedence::CALL,
         token::TokenType::LBRACKET => return Precedence::INDEX,
         _ => return Precedence::LOWEST,
     }
 }
 
+#[derive(PartialEq, Debug, Clone)]
+pub enum ParseErrorKind {
+    UnexpectedToken,
+    NoPrefixParseFn,
+    InvalidInteger(String),
+    InvalidFloat(String),
+    OutsideLoop,
+    PositionalAfterNamed,
+    Lexical(String),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: token::Span,
+    pub expected: Option<token::TokenType>,
+    pub found: Option<token::TokenType>,
+}
+
+impl ParseError {
+    pub fn message(&self) -> String {
+        let found = match &self.found {
+            Some(found) => found.to_string(),
+            None => "nothing".to_string(),
+        };
+        match &self.kind {
+            ParseErrorKind::UnexpectedToken => match &self.expected {
+                Some(expected) => format!("expected {}, found {}", expected, found),
+                None => format!("unexpected {}", found),
+            },
+            ParseErrorKind::NoPrefixParseFn => format!("expected an expression, found {}", found),
+            ParseErrorKind::InvalidInteger(literal) => {
+                format!("could not parse {} as integer", literal)
+            }
+            ParseErrorKind::InvalidFloat(literal) => {
+                format!("could not parse {} as float", literal)
+            }
+            ParseErrorKind::OutsideLoop => format!("{} outside of a loop", found),
+            ParseErrorKind::PositionalAfterNamed => {
+                "positional argument after named arguments".to_string()
+            }
+            ParseErrorKind::Lexical(message) => message.clone(),
+        }
+    }
+
+    // A hint on how to fix the error, when there is a useful one.
+    pub fn help(&self) -> Option<String> {
+        match (&self.kind, &self.expected, &self.found) {
+            (
+                ParseErrorKind::UnexpectedToken,
+                Some(
+                    expected @ (token::TokenType::RPAREN
+                    | token::TokenType::RBRACKET
+                    | token::TokenType::RBRACE),
+                ),
+                Some(token::TokenType::EOF),
+            ) => Some(format!("add the missing {}", expected)),
+            (
+                ParseErrorKind::UnexpectedToken,
+                Some(expected @ (token::TokenType::RPAREN | token::TokenType::RBRACKET)),
+                _,
+            ) => Some(format!(
+                "separate items with `,` and close the list with {}",
+                expected
+            )),
+            (ParseErrorKind::UnexpectedToken, Some(token::TokenType::ASSIGN), _) => {
+                Some("a binding looks like `let x = 5;`".to_string())
+            }
+            (
+                ParseErrorKind::NoPrefixParseFn,
+                _,
+                Some(
+                    token::TokenType::SEMICOLON
+                    | token::TokenType::RPAREN
+                    | token::TokenType::RBRACKET
+                    | token::TokenType::RBRACE
+                    | token::TokenType::EOF,
+                ),
+            ) => Some("an expression is missing here".to_string()),
+            (ParseErrorKind::InvalidInteger(_), _, _) => Some(format!(
+                "integers range from {} to {}",
+                i64::MIN,
+                i64::MAX
+            )),
+            (ParseErrorKind::OutsideLoop, _, _) => Some(
+                "`break` and `continue` only work inside `while` and `for` loops of the same function"
+                    .to_string(),
+            ),
+            (ParseErrorKind::PositionalAfterNamed, _, _) => {
+                Some("named arguments go last, e.g. `f(1, y: 2)`".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message())
+    }
+}
+
+// The result of parsing a whole input.
+pub enum ParseOutcome {
+    Complete(ast::Program),
+    // The input stops in the middle of a construct, e.g. inside an open `(`,
+    // `[`, `{`, string or block comment. More input could still make it
+    // valid; the errors describe what is missing.
+    Incomplete(Vec<ParseError>),
+    Invalid(Vec<ParseError>),
+}
+
 #[allow(dead_code)]
-pub struct Parser {
-    l: lexer::Lexer,
-    cur_token: Rc<token::Token>,
-    peek_token: Rc<token::Token>,
-    pub errors: Vec<String>,
+pub struct Parser<'a> {
+    l: lexer::Lexer<'a>,
+    cur_token: Rc<token::Token<'a>>,
+    peek_token: Rc<token::Token<'a>>,
+    pub errors: Vec<ParseError>,
+    loop_depth: usize,
+    // Number of `{` not yet closed, up to and including the current token.
+    brace_depth: usize,
 }
 
 #[allow(dead_code)]
-impl Parser {
-    pub fn new(l: lexer::Lexer) -> Parser {
+impl<'a> Parser<'a> {
+    pub fn new(l: lexer::Lexer<'a>) -> Parser<'a> {
         let mut p = Parser {
             l: l,
             cur_token: Rc::new(token::Token {
                 token_type: token::TokenType::ILLEGAL,
-                literal: "".to_string(),
+                literal: Cow::Borrowed(""),
+                span: token::Span::default(),
             }),
             peek_token: Rc::new(token::Token {
                 token_type: token::TokenType::ILLEGAL,
-                literal: "".to_string(),
+                literal: Cow::Borrowed(""),
+                span: token::Span::default(),
             }),
             errors: Vec::new(),
+            loop_depth: 0,
+            brace_depth: 0,
         };
 
         p.next_token();
@@ -67,15 +210,56 @@ impl Parser {
     }
 
     fn peek_error(&mut self, t: token::TokenType) {
-        self.errors.push(String::from(format!(
-            "\r\nexpected next token to be {:?}, got {:?} instead.",
-            t, self.peek_token.token_type
-        )))
+        self.errors.push(ParseError {
+            kind: ParseErrorKind::UnexpectedToken,
+            span: self.peek_token.span.clone(),
+            expected: Some(t),
+            found: Some(self.peek_token.token_type.clone()),
+        })
+    }
+
+    fn cur_error(&mut self, kind: ParseErrorKind) {
+        self.errors.push(ParseError {
+            kind,
+            span: self.cur_token.span.clone(),
+            expected: None,
+            found: Some(self.cur_token.token_type.clone()),
+        })
     }
 
     fn next_token(&mut self) {
         self.cur_token = Rc::clone(&self.peek_token);
         self.peek_token = Rc::new(self.l.next_token());
+        match self.cur_token.token_type {
+            token::TokenType::LBRACE => self.brace_depth += 1,
+            token::TokenType::RBRACE => self.brace_depth = self.brace_depth.saturating_sub(1),
+            _ => {}
+        }
+        for (span, message) in self.l.errors.drain(..) {
+            self.errors.push(ParseError {
+                kind: ParseErrorKind::Lexical(message),
+                span,
+                expected: None,
+                found: None,
+            });
+        }
+    }
+
+    pub fn parse(mut self) -> ParseOutcome {
+        let program = self.parse_program();
+        if self.errors.is_empty() {
+            return ParseOutcome::Complete(program);
+        }
+
+        let incomplete = self.errors.iter().all(|err| match err.kind {
+            ParseErrorKind::Lexical(_) => self.l.unterminated,
+            _ => err.found == Some(token::TokenType::EOF),
+        });
+        if incomplete {
+            ParseOutcome::Incomplete(self.errors)
+        } else {
+            ParseOutcome::Invalid(self.errors)
+        }
     }
 
     pub fn parse_program(&mut self) -> ast::Program {
@@ -85,25 +269,76 @@ impl Parser {
         while self.cur_token.token_type != token::TokenType::EOF {
             if let Some(stmt) = self.parse_statement() {
                 program.statements.push(stmt);
+            } else {
+                self.synchronize();
             }
             self.next_token();
         }
         return program;
     }
+
+    // Panic-mode recovery after a statement failed to parse: skip to the end
+    // of the broken statement so that the next one is parsed from a clean
+    // start. Stops on a `;` or right before a keyword that starts a statement
+    // or a `}` that closes the enclosing block, ignoring anything inside
+    // braces opened by the broken statement.
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.cur_token.token_type {
+                token::TokenType::EOF => return,
+                token::TokenType::SEMICOLON if depth == 0 => return,
+                token::TokenType::LBRACE => depth += 1,
+                token::TokenType::RBRACE if depth > 0 => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                match self.peek_token.token_type {
+                    token::TokenType::EOF
+                    | token::TokenType::RBRACE
+                    | token::TokenType::LET
+                    | token::TokenType::RETURN
+                    | token::TokenType::IF
+                    | token::TokenType::WHILE
+                    | token::TokenType::FOR
+                    | token::TokenType::BREAK
+                    | token::TokenType::CONTINUE
+                    | token::TokenType::TRY
+                    | token::TokenType::THROW
+                    | token::TokenType::FUNCTION
+                    | token::TokenType::MATCH => return,
+                    _ => {}
+                }
+            }
+            self.next_token();
+        }
+    }
+
+    // Whether the last error was reported on the current token, i.e. the
+    // parser stopped on it instead of consuming it.
+    fn failed_on_cur_token(&self) -> bool {
+        match self.errors.last() {
+            Some(err) => err.span == self.cur_token.span,
+            None => false,
+        }
+    }
     fn parse_statement(&mut self) -> Option<ast::Statement> {
         match self.cur_token.token_type {
             token::TokenType::LET => return self.parse_let_statement(),
             token::TokenType::RETURN => return self.parse_return_statement(),
+            token::TokenType::BREAK | token::TokenType::CONTINUE => {
+                self.parse_loop_control_statement()
+            }
+            token::TokenType::FUNCTION if self.peek_token_is(&token::TokenType::IDENT) => {
+                self.parse_function_declaration()
+            }
+            token::TokenType::THROW => self.parse_throw_statement(),
             _ => return self.parse_expression_statement(),
         }
     }
     fn parse_let_statement(&mut self) -> Option<ast::Statement> {
-        if !self.expect_peek(token::TokenType::IDENT) {
-            return None;
-        }
-        let name = ast::Expression::Identifier {
-            value: self.cur_token.literal.clone(),
-        };
+        let start = self.cur_token.span.clone();
+        let name = self.parse_pattern(false)?;
         if !self.expect_peek(token::TokenType::ASSIGN) {
             return None;
         }
@@ -111,41 +346,218 @@ impl Parser {
         self.next_token();
 
         if let Some(expression) = self.parse_expression(Precedence::LOWEST) {
+            if self.peek_token_is(&token::TokenType::SEMICOLON) {
+                self.next_token();
+            }
             let stmt = ast::Statement::LetStatement {
                 name,
                 value: expression,
+                span: start.to(&self.cur_token.span),
             };
-            if self.peek_token_is(&token::TokenType::SEMICOLON) {
-                self.next_token();
-            }
             return Some(stmt);
         } else {
             return None;
         }
     }
+    // Parses the pattern following the current token: a name, `[a, b, ...rest]`
+    // or `{a, b: pattern}`. Patterns nest. `refutable` patterns, as used by
+    // `match`, may also contain literals such as `1`, `-2.5` or `"a"`.
+    fn parse_pattern(&mut self, refutable: bool) -> Option<ast::Expression> {
+        match self.peek_token.token_type {
+            token::TokenType::LBRACKET => {
+                self.next_token();
+                return self.parse_array_pattern(refutable);
+            }
+            token::TokenType::LBRACE => {
+                self.next_token();
+                return self.parse_hash_pattern(refutable);
+            }
+            token::TokenType::INT
+            | token::TokenType::FLOAT
+            | token::TokenType::STRING
+            | token::TokenType::TRUE
+            | token::TokenType::FALSE
+                if refutable =>
+            {
+                self.next_token();
+                return self.parse_prefix_expression_fns();
+            }
+            token::TokenType::MINUS if refutable => {
+                self.next_token();
+                let start = self.cur_token.span.clone();
+                if !self.peek_token_is(&token::TokenType::FLOAT) {
+                    if !self.expect_peek(token::TokenType::INT) {
+                        return None;
+                    }
+                } else {
+                    self.next_token();
+                }
+                let right = self.parse_prefix_expression_fns()?;
+                return Some(ast::Expression::PrefixExpression {
+                    operator: "-".to_string(),
+                    right: Box::new(right),
+                    span: start.to(&self.cur_token.span),
+                });
+            }
+            _ => {}
+        }
+        if !self.expect_peek(token::TokenType::IDENT) {
+            return None;
+        }
+        Some(self.parse_identifier())
+    }
+
+    fn parse_array_pattern(&mut self, refutable: bool) -> Option<ast::Expression> {
+        let start = self.cur_token.span.clone();
+        let mut elements = Vec::new();
+        let mut rest = None;
+
+        if !self.peek_token_is(&token::TokenType::RBRACKET) {
+            loop {
+                if self.peek_token_is(&token::TokenType::ELLIPSIS) {
+                    self.next_token();
+                    if !self.expect_peek(token::TokenType::IDENT) {
+                        return None;
+                    }
+                    rest = Some(Box::new(self.parse_identifier()));
+                    break;
+                }
+                elements.push(self.parse_pattern(refutable)?);
+                if !self.peek_token_is(&token::TokenType::COMMA) {
+                    break;
+                }
+                self.next_token();
+            }
+        }
+        if !self.expect_peek(token::TokenType::RBRACKET) {
+            return None;
+        }
+
+        Some(ast::Expression::ArrayPattern {
+            elements,
+            rest,
+            span: start.to(&self.cur_token.span),
+        })
+    }
+
+    fn parse_hash_pattern(&mut self, refutable: bool) -> Option<ast::Expression> {
+        let start = self.cur_token.span.clone();
+        let depth = self.brace_depth - 1;
+        let mut entries = Vec::new();
+
+        let mut ok = true;
+        if !self.peek_token_is(&token::TokenType::RBRACE) {
+            loop {
+                if !self.expect_peek(token::TokenType::IDENT) {
+                    ok = false;
+                    break;
+                }
+                let key = self.parse_identifier();
+                let mut pattern = None;
+                if self.peek_token_is(&token::TokenType::COLON) {
+                    self.next_token();
+                    match self.parse_pattern(refutable) {
+                        Some(p) => pattern = Some(p),
+                        None => {
+                            ok = false;
+                            break;
+                        }
+                    }
+                }
+                entries.push((key, pattern));
+                if !self.peek_token_is(&token::TokenType::COMMA) {
+                    break;
+                }
+                self.next_token();
+            }
+        }
+        if !ok || !self.expect_peek(token::TokenType::RBRACE) {
+            self.skip_to_closing_brace(depth);
+            return None;
+        }
+
+        Some(ast::Expression::HashPattern {
+            entries,
+            span: start.to(&self.cur_token.span),
+        })
+    }
+
+    // After an error inside a `{` opened at brace depth `depth`, skips up to
+    // its `}` so that recovery does not take it for the end of an enclosing
+    // block. Gives up at a `;` directly inside the braces.
+    fn skip_to_closing_brace(&mut self, depth: usize) {
+        while self.brace_depth > depth
+            && !self.peek_token_is(&token::TokenType::EOF)
+            && !(self.brace_depth == depth + 1 && self.peek_token_is(&token::TokenType::SEMICOLON))
+        {
+            self.next_token();
+        }
+    }
+
     fn parse_return_statement(&mut self) -> Option<ast::Statement> {
+        let start = self.cur_token.span.clone();
         self.next_token();
 
         if let Some(expression) = self.parse_expression(Precedence::LOWEST) {
-            let stmt = ast::Statement::ReturnStatement {
-                return_value: expression,
-            };
             if self.peek_token_is(&token::TokenType::SEMICOLON) {
                 self.next_token();
             }
+            let stmt = ast::Statement::ReturnStatement {
+                return_value: expression,
+                span: start.to(&self.cur_token.span),
+            };
             return Some(stmt);
         } else {
             return None;
         }
     }
+    fn parse_throw_statement(&mut self) -> Option<ast::Statement> {
+        let start = self.cur_token.span.clone();
+        self.next_token();
+
+        let value = self.parse_expression(Precedence::LOWEST)?;
+        if self.peek_token_is(&token::TokenType::SEMICOLON) {
+            self.next_token();
+        }
+        Some(ast::Statement::ThrowStatement {
+            value,
+            span: start.to(&self.cur_token.span),
+        })
+    }
+    fn parse_loop_control_statement(&mut self) -> Option<ast::Statement> {
+        let token = Rc::clone(&self.cur_token);
+        if self.peek_token_is(&token::TokenType::SEMICOLON) {
+            self.next_token();
+        }
+
+        if self.loop_depth == 0 {
+            self.errors.push(ParseError {
+                kind: ParseErrorKind::OutsideLoop,
+                span: token.span.clone(),
+                expected: None,
+                found: Some(token.token_type.clone()),
+            });
+            return None;
+        }
+
+        let span = token.span.to(&self.cur_token.span);
+        if token.token_type == token::TokenType::BREAK {
+            Some(ast::Statement::BreakStatement { span })
+        } else {
+            Some(ast::Statement::ContinueStatement { span })
+        }
+    }
+
     fn parse_expression_statement(&mut self) -> Option<ast::Statement> {
+        let start = self.cur_token.span.clone();
         if let Some(expression) = self.parse_expression(Precedence::LOWEST) {
-            let stmt = ast::Statement::ExpressionStatement {
-                expression: expression,
-            };
             if self.peek_token_is(&token::TokenType::SEMICOLON) {
                 self.next_token();
             }
+            let stmt = ast::Statement::ExpressionStatement {
+                expression,
+                span: start.to(&self.cur_token.span),
+            };
             return Some(stmt);
         } else {
             return None;
@@ -153,6 +565,7 @@ impl Parser {
     }
 
     fn parse_block_statement(&mut self) -> Option<ast::Statement> {
+        let start = self.cur_token.span.clone();
         let mut statements = Vec::new();
 
         self.next_token();
@@ -162,42 +575,48 @@ impl Parser {
         {
             if let Some(stmt) = self.parse_statement() {
                 statements.push(stmt);
+            } else if self.cur_token_is(&token::TokenType::RBRACE) && self.failed_on_cur_token() {
+                // The broken statement ran into the `}` closing this block.
+                break;
+            } else {
+                self.synchronize();
             }
             self.next_token();
         }
 
         if self.cur_token_is(&token::TokenType::EOF) {
+            self.errors.push(ParseError {
+                kind: ParseErrorKind::UnexpectedToken,
+                span: self.cur_token.span.clone(),
+                expected: Some(token::TokenType::RBRACE),
+                found: Some(token::TokenType::EOF),
+            });
             return None;
         }
 
-        return Some(ast::Statement::BlockStatement { statements });
+        return Some(ast::Statement::BlockStatement {
+            statements,
+            span: start.to(&self.cur_token.span),
+        });
     }
 
     fn parse_expression(&mut self, precedence: Precedence) -> Option<ast::Expression> {
-        if let Some(left_exp) = self.parse_prefix_expression_fns() {
-            let mut left = Box::new(left_exp);
-            while !self.peek_token_is(&token::TokenType::SEMICOLON)
-                && precedence < self.peek_precedence()
-            {
-                self.next_token();
-                if let Some(left_exp_new) = self.parse_infix_expression_fns(left.clone()) {
-                    left = Box::new(left_exp_new);
-                } else {
-                    return Some(*left);
-                }
-            }
-
-            return Some(*left);
-        } else {
-            self.no_prefix_parse_fn_error();
-            return None;
+        let mut left = Box::new(self.parse_prefix_expression_fns()?);
+        while !self.peek_token_is(&token::TokenType::SEMICOLON)
+            && precedence < self.peek_precedence()
+        {
+            self.next_token();
+            left = Box::new(self.parse_infix_expression_fns(left)?);
         }
+
+        return Some(*left);
     }
 
     fn parse_prefix_expression_fns(&mut self) -> Option<ast::Expression> {
         match self.cur_token.token_type {
             token::TokenType::IDENT => return Some(self.parse_identifier()),
             token::TokenType::INT => return self.parse_integer_literal(),
+            token::TokenType::FLOAT => return self.parse_float_literal(),
             token::TokenType::STRING => return self.parse_string_literal(),
             token::TokenType::BANG => return self.parse_prefix_expression(),
             token::TokenType::MINUS => return self.parse_prefix_expression(),
@@ -207,9 +626,15 @@ impl Parser {
             token::TokenType::LBRACKET => return self.parse_array_literal(),
             token::TokenType::IF => return self.parse_if_expression(),
             token::TokenType::WHILE => return self.parse_while_expression(),
+            token::TokenType::FOR => self.parse_for_expression(),
+            token::TokenType::MATCH => self.parse_match_expression(),
+            token::TokenType::TRY => self.parse_try_expression(),
             token::TokenType::FUNCTION => return self.parse_function_literal(),
             token::TokenType::LBRACE => return self.parse_hash_literal(),
-            _ => return None,
+            _ => {
+                self.cur_error(ParseErrorKind::NoPrefixParseFn);
+                return None;
+            }
         }
     }
 
@@ -219,15 +644,30 @@ impl Parser {
     ) -> Option<ast::Expression> {
         match self.cur_token.token_type {
             token::TokenType::PLUS => return self.parse_infix_expression(left_exp),
-            token::TokenType::DOT => return self.parse_infix_expression(left_exp),
             token::TokenType::MINUS => return self.parse_infix_expression(left_exp),
             token::TokenType::SLASH => return self.parse_infix_expression(left_exp),
             token::TokenType::ASTERISK => return self.parse_infix_expression(left_exp),
-            token::TokenType::ASSIGN => return self.parse_assign_expression(left_exp),
+            token::TokenType::PERCENT => return self.parse_infix_expression(left_exp),
+            token::TokenType::POWER => return self.parse_infix_expression(left_exp),
+            token::TokenType::ASSIGN
+            | token::TokenType::PLUSASSIGN
+            | token::TokenType::MINUSASSIGN
+            | token::TokenType::ASTERISKASSIGN
+            | token::TokenType::SLASHASSIGN
+            | token::TokenType::PERCENTASSIGN => return self.parse_assign_expression(left_exp),
             token::TokenType::EQ => return self.parse_infix_expression(left_exp),
             token::TokenType::NOTEQ => return self.parse_infix_expression(left_exp),
             token::TokenType::LT => return self.parse_infix_expression(left_exp),
             token::TokenType::GT => return self.parse_infix_expression(left_exp),
+            token::TokenType::LTEQ => return self.parse_infix_expression(left_exp),
+            token::TokenType::GTEQ => return self.parse_infix_expression(left_exp),
+            token::TokenType::AND => return self.parse_infix_expression(left_exp),
+            token::TokenType::OR => return self.parse_infix_expression(left_exp),
+            token::TokenType::AMPERSAND => return self.parse_infix_expression(left_exp),
+            token::TokenType::PIPE => return self.parse_infix_expression(left_exp),
+            token::TokenType::CARET => return self.parse_infix_expression(left_exp),
+            token::TokenType::LSHIFT => return self.parse_infix_expression(left_exp),
+            token::TokenType::RSHIFT => return self.parse_infix_expression(left_exp),
             token::TokenType::LPAREN => return self.parse_call_expression(left_exp),
             token::TokenType::LBRACKET => return self.parse_index_expression(left_exp),
             _ => return None,
@@ -242,6 +682,7 @@ impl Parser {
             }
 
             return Some(ast::Expression::IndexExpression {
+                span: left.span().to(&self.cur_token.span),
                 left,
                 index: Box::new(index),
             });
@@ -250,7 +691,8 @@ impl Parser {
     }
 
     fn parse_prefix_expression(&mut self) -> Option<ast::Expression> {
-        let expression_operator = self.cur_token.literal.clone();
+        let start = self.cur_token.span.clone();
+        let expression_operator = self.cur_token.literal.to_string();
 
         self.next_token();
 
@@ -258,6 +700,7 @@ impl Parser {
             return Some(ast::Expression::PrefixExpression {
                 operator: expression_operator,
                 right: Box::new(right),
+                span: start.to(&self.cur_token.span),
             });
         } else {
             return None;
@@ -265,11 +708,14 @@ impl Parser {
     }
 
     fn parse_assign_expression(&mut self, left: Box<ast::Expression>) -> Option<ast::Expression> {
+        let operator = self.cur_token.literal.to_string();
         let precedence = self.cur_precedence();
         self.next_token();
         if let Some(right) = self.parse_expression(precedence) {
             return Some(ast::Expression::AssignExpression {
+                span: left.span().to(&self.cur_token.span),
                 left,
+                operator,
                 right: Box::new(right),
             });
         } else {
@@ -278,12 +724,17 @@ impl Parser {
     }
 
     fn parse_infix_expression(&mut self, left: Box<ast::Expression>) -> Option<ast::Expression> {
-        let operator = self.cur_token.literal.clone();
+        let operator = self.cur_token.literal.to_string();
 
-        let precedence = self.cur_precedence();
+        let mut precedence = self.cur_precedence();
+        // `**` is right-associative: `2 ** 3 ** 2` is `2 ** (3 ** 2)`.
+        if precedence == Precedence::POWER {
+            precedence = Precedence::PREFIX;
+        }
         self.next_token();
         if let Some(right) = self.parse_expression(precedence) {
             return Some(ast::Expression::InfixExpression {
+                span: left.span().to(&self.cur_token.span),
                 left,
                 operator,
                 right: Box::new(right),
@@ -294,15 +745,41 @@ impl Parser {
     }
 
     fn parse_call_expression(&mut self, function: Box<ast::Expression>) -> Option<ast::Expression> {
-        match self.parse_expression_list(token::TokenType::RPAREN) {
-            Some(arguments) => {
-                return Some(ast::Expression::CallExpression {
-                    function,
-                    arguments,
-                })
+        let mut arguments = Vec::new();
+        let mut named_arguments = Vec::new();
+
+        if !self.peek_token_is(&token::TokenType::RPAREN) {
+            loop {
+                self.next_token();
+                if self.cur_token_is(&token::TokenType::IDENT)
+                    && self.peek_token_is(&token::TokenType::COLON)
+                {
+                    let name = self.parse_identifier();
+                    self.next_token();
+                    self.next_token();
+                    named_arguments.push((name, self.parse_expression(Precedence::LOWEST)?));
+                } else if !named_arguments.is_empty() {
+                    self.cur_error(ParseErrorKind::PositionalAfterNamed);
+                    return None;
+                } else {
+                    arguments.push(self.parse_expression(Precedence::LOWEST)?);
+                }
+                if !self.peek_token_is(&token::TokenType::COMMA) {
+                    break;
+                }
+                self.next_token();
             }
-            None => return None,
         }
+        if !self.expect_peek(token::TokenType::RPAREN) {
+            return None;
+        }
+
+        Some(ast::Expression::CallExpression {
+            span: function.span().to(&self.cur_token.span),
+            function,
+            arguments,
+            named_arguments,
+        })
     }
 
     fn parse_expression_list(&mut self, end: token::TokenType) -> Option<Vec<ast::Expression>> {
@@ -337,6 +814,7 @@ impl Parser {
     }
 
     fn parse_if_expression(&mut self) -> Option<ast::Expression> {
+        let start = self.cur_token.span.clone();
         if !self.expect_peek(token::TokenType::LPAREN) {
             return None;
         }
@@ -356,6 +834,23 @@ impl Parser {
                         if self.peek_token_is(&token::TokenType::ELSE) {
                             self.next_token();
 
+                            if self.peek_token_is(&token::TokenType::IF) {
+                                self.next_token();
+                                let else_if = self.parse_if_expression()?;
+
+                                // `else if` is an `else` block holding a single if expression.
+                                let alternative = ast::Statement::ExpressionStatement {
+                                    span: else_if.span().clone(),
+                                    expression: else_if,
+                                };
+                                return Some(ast::Expression::IfExpression {
+                                    condition: Box::new(condition),
+                                    consequence: Box::new(consequence),
+                                    alternative: Some(Box::new(alternative)),
+                                    span: start.to(&self.cur_token.span),
+                                });
+                            }
+
                             if !self.expect_peek(token::TokenType::LBRACE) {
                                 return None;
                             }
@@ -366,10 +861,11 @@ impl Parser {
                                         condition: Box::new(condition),
                                         consequence: Box::new(consequence),
                                         alternative: Some(Box::new(alternative)),
+                                        span: start.to(&self.cur_token.span),
                                     };
                                     return Some(expression);
                                 }
-                                None => return Some(ast::Expression::NeedNext),
+                                None => return None,
                             }
                         }
 
@@ -377,17 +873,144 @@ impl Parser {
                             condition: Box::new(condition),
                             consequence: Box::new(consequence),
                             alternative: None,
+                            span: start.to(&self.cur_token.span),
                         };
                         return Some(expression);
                     }
-                    None => return Some(ast::Expression::NeedNext),
+                    None => return None,
                 }
             }
             None => return None,
         }
     }
 
+    fn parse_match_expression(&mut self) -> Option<ast::Expression> {
+        let start = self.cur_token.span.clone();
+        if !self.expect_peek(token::TokenType::LPAREN) {
+            return None;
+        }
+        self.next_token();
+        let subject = self.parse_expression(Precedence::LOWEST)?;
+        if !self.expect_peek(token::TokenType::RPAREN) {
+            return None;
+        }
+        if !self.expect_peek(token::TokenType::LBRACE) {
+            return None;
+        }
+        let depth = self.brace_depth - 1;
+
+        // Arms are separated by `,`, which is optional after a block.
+        let mut arms = Vec::new();
+        while !self.peek_token_is(&token::TokenType::RBRACE) {
+            let arm = match self.parse_match_arm() {
+                Some(arm) => arm,
+                None => {
+                    self.skip_to_closing_brace(depth);
+                    return None;
+                }
+            };
+            let block = matches!(arm.body, ast::Statement::BlockStatement { .. });
+            arms.push(arm);
+            if self.peek_token_is(&token::TokenType::COMMA) {
+                self.next_token();
+            } else if !block {
+                break;
+            }
+        }
+        if !self.expect_peek(token::TokenType::RBRACE) {
+            self.skip_to_closing_brace(depth);
+            return None;
+        }
+
+        Some(ast::Expression::MatchExpression {
+            subject: Box::new(subject),
+            arms,
+            span: start.to(&self.cur_token.span),
+        })
+    }
+
+    fn parse_match_arm(&mut self) -> Option<ast::MatchArm> {
+        let start = self.peek_token.span.clone();
+        let pattern = self.parse_pattern(true)?;
+        let mut guard = None;
+        if self.peek_token_is(&token::TokenType::IF) {
+            self.next_token();
+            self.next_token();
+            guard = Some(self.parse_expression(Precedence::LOWEST)?);
+        }
+        if !self.expect_peek(token::TokenType::ARROW) {
+            return None;
+        }
+
+        let body = if self.peek_token_is(&token::TokenType::LBRACE) {
+            self.next_token();
+            self.parse_block_statement()?
+        } else {
+            self.next_token();
+            let body_start = self.cur_token.span.clone();
+            let expression = self.parse_expression(Precedence::LOWEST)?;
+            ast::Statement::ExpressionStatement {
+                expression,
+                span: body_start.to(&self.cur_token.span),
+            }
+        };
+
+        Some(ast::MatchArm {
+            pattern,
+            guard,
+            body,
+            span: start.to(&self.cur_token.span),
+        })
+    }
+
+    fn parse_try_expression(&mut self) -> Option<ast::Expression> {
+        let start = self.cur_token.span.clone();
+        if !self.expect_peek(token::TokenType::LBRACE) {
+            return None;
+        }
+        let body = self.parse_block_statement()?;
+
+        let mut catch = None;
+        if self.peek_token_is(&token::TokenType::CATCH) {
+            self.next_token();
+            if !self.expect_peek(token::TokenType::LPAREN) {
+                return None;
+            }
+            let parameter = self.parse_pattern(false)?;
+            if !self.expect_peek(token::TokenType::RPAREN) {
+                return None;
+            }
+            if !self.expect_peek(token::TokenType::LBRACE) {
+                return None;
+            }
+            let block = self.parse_block_statement()?;
+            catch = Some((Box::new(parameter), Box::new(block)));
+        }
+
+        let mut finally = None;
+        if self.peek_token_is(&token::TokenType::FINALLY) {
+            self.next_token();
+            if !self.expect_peek(token::TokenType::LBRACE) {
+                return None;
+            }
+            finally = Some(Box::new(self.parse_block_statement()?));
+        }
+
+        if catch.is_none() && finally.is_none() {
+            self.peek_error(token::TokenType::CATCH);
+            return None;
+        }
+
+        Some(ast::Expression::TryExpression {
+            body: Box::new(body),
+            catch,
+            finally,
+            span: start.to(&self.cur_token.span),
+        })
+    }
+
     fn parse_while_expression(&mut self) -> Option<ast::Expression> {
+        let start = self.cur_token.span.clone();
         if !self.expect_peek(token::TokenType::LPAREN) {
             return None;
         }
@@ -402,46 +1025,173 @@ impl Parser {
                     return None;
                 }
 
-                match self.parse_block_statement() {
+                self.loop_depth += 1;
+                let body = self.parse_block_statement();
+                self.loop_depth -= 1;
+
+                match body {
                     Some(consequence) => {
                         let expression = ast::Expression::WhileExpression {
                             condition: Box::new(condition),
                             consequence: Box::new(consequence),
+                            span: start.to(&self.cur_token.span),
                         };
                         return Some(expression);
                     }
-                    None => return Some(ast::Expression::NeedNext),
+                    None => return None,
                 }
             }
             None => return None,
         }
     }
 
+    fn parse_for_expression(&mut self) -> Option<ast::Expression> {
+        let start = self.cur_token.span.clone();
+        if !self.expect_peek(token::TokenType::LPAREN) {
+            return None;
+        }
+
+        let mut variables = Vec::new();
+        loop {
+            if !self.expect_peek(token::TokenType::IDENT) {
+                return None;
+            }
+            variables.push(ast::Expression::Identifier {
+                value: self.cur_token.literal.to_string(),
+                span: self.cur_token.span.clone(),
+            });
+            if variables.len() == 2 || !self.peek_token_is(&token::TokenType::COMMA) {
+                break;
+            }
+            self.next_token();
+        }
+
+        if !self.expect_peek(token::TokenType::IN) {
+            return None;
+        }
+        self.next_token();
+        let iterable = self.parse_expression(Precedence::LOWEST)?;
+        if !self.expect_peek(token::TokenType::RPAREN) {
+            return None;
+        }
+        if !self.expect_peek(token::TokenType::LBRACE) {
+            return None;
+        }
+
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+
+        match body {
+            Some(body) => Some(ast::Expression::ForExpression {
+                variables,
+                iterable: Box::new(iterable),
+                body: Box::new(body),
+                span: start.to(&self.cur_token.span),
+            }),
+            None => None,
+        }
+    }
+
     fn parse_function_literal(&mut self) -> Option<ast::Expression> {
+        let start = self.cur_token.span.clone();
         if !self.expect_peek(token::TokenType::LPAREN) {
             return None;
         }
 
-        match self.parse_expression_list(token::TokenType::RPAREN) {
-            Some(parameters) => {
-                if !self.expect_peek(token::TokenType::LBRACE) {
+        match self.parse_function_rest() {
+            Some((parameters, body)) => {
+                return Some(ast::Expression::FunctionLiteral {
+                    parameters,
+                    body: Box::new(body),
+                    span: start.to(&self.cur_token.span),
+                })
+            }
+            None => return None,
+        }
+    }
+
+    fn parse_function_declaration(&mut self) -> Option<ast::Statement> {
+        let start = self.cur_token.span.clone();
+        self.next_token();
+        let name = self.cur_token.literal.to_string();
+        if !self.expect_peek(token::TokenType::LPAREN) {
+            return None;
+        }
+
+        let (parameters, body) = self.parse_function_rest()?;
+        let span = start.to(&self.cur_token.span);
+        if self.peek_token_is(&token::TokenType::SEMICOLON) {
+            self.next_token();
+        }
+        Some(ast::Statement::FunctionDeclaration {
+            name,
+            parameters,
+            body: Box::new(body),
+            span,
+        })
+    }
+
+    // The parameters and body of a function, starting at its `(`.
+    fn parse_function_rest(&mut self) -> Option<(Vec<ast::Parameter>, ast::Statement)> {
+        let parameters = self.parse_parameters()?;
+        if !self.expect_peek(token::TokenType::LBRACE) {
+            return None;
+        }
+
+        // `break`/`continue` cannot cross a function boundary.
+        let loop_depth = self.loop_depth;
+        self.loop_depth = 0;
+        let body = self.parse_block_statement();
+        self.loop_depth = loop_depth;
+
+        Some((parameters, body?))
+    }
+
+    fn parse_parameters(&mut self) -> Option<Vec<ast::Parameter>> {
+        let mut parameters = Vec::new();
+
+        if !self.peek_token_is(&token::TokenType::RPAREN) {
+            loop {
+                let start = self.peek_token.span.clone();
+                let rest = self.peek_token_is(&token::TokenType::ELLIPSIS);
+                if rest {
+                    self.next_token();
+                }
+                if !self.expect_peek(token::TokenType::IDENT) {
                     return None;
                 }
-                match self.parse_block_statement() {
-                    Some(body) => {
-                        return Some(ast::Expression::FunctionLiteral {
-                            parameters,
-                            body: Box::new(body),
-                        })
-                    }
-                    None => return Some(ast::Expression::NeedNext),
+                let name = self.cur_token.literal.to_string();
+
+                let mut default = None;
+                if !rest && self.peek_token_is(&token::TokenType::ASSIGN) {
+                    self.next_token();
+                    self.next_token();
+                    default = Some(self.parse_expression(Precedence::LOWEST)?);
                 }
+                parameters.push(ast::Parameter {
+                    name,
+                    default,
+                    rest,
+                    span: start.to(&self.cur_token.span),
+                });
+
+                // A rest parameter has to be the last one.
+                if rest || !self.peek_token_is(&token::TokenType::COMMA) {
+                    break;
+                }
+                self.next_token();
             }
-            None => return None,
         }
+        if !self.expect_peek(token::TokenType::RPAREN) {
+            return None;
+        }
+
+        Some(parameters)
     }
 
     fn parse_hash_literal(&mut self) -> Option<ast::Expression> {
+        let start = self.cur_token.span.clone();
         let mut pairs = Vec::new();
 
         while !self.peek_token_is(&token::TokenType::RBRACE) {
@@ -467,36 +1217,57 @@ impl Parser {
             return None;
         }
 
-        Some(ast::Expression::HashLiteral { pairs })
+        Some(ast::Expression::HashLiteral {
+            pairs,
+            span: start.to(&self.cur_token.span),
+        })
     }
 
     fn parse_identifier(&self) -> ast::Expression {
         return ast::Expression::Identifier {
-            value: self.cur_token.literal.clone(),
+            value: self.cur_token.literal.to_string(),
+            span: self.cur_token.span.clone(),
         };
     }
 
     fn parse_integer_literal(&mut self) -> Option<ast::Expression> {
         if let Ok(value) = self.cur_token.literal.parse::<i64>() {
-            return Some(ast::Expression::IntegerLiteral { value });
+            return Some(ast::Expression::IntegerLiteral {
+                value,
+                span: self.cur_token.span.clone(),
+            });
         } else {
-            self.errors.push(format!(
-                "could not parse {} as integer",
-                self.cur_token.literal
-            ));
+            let literal = self.cur_token.literal.to_string();
+            self.cur_error(ParseErrorKind::InvalidInteger(literal));
             return None;
         }
     }
 
+    fn parse_float_literal(&mut self) -> Option<ast::Expression> {
+        match self.cur_token.literal.parse::<f64>() {
+            Ok(value) => Some(ast::Expression::FloatLiteral {
+                value,
+                span: self.cur_token.span.clone(),
+            }),
+            Err(_) => {
+                let literal = self.cur_token.literal.to_string();
+                self.cur_error(ParseErrorKind::InvalidFloat(literal));
+                None
+            }
+        }
+    }
+
     fn parse_string_literal(&mut self) -> Option<ast::Expression> {
         return Some(ast::Expression::StringLiteral {
-            value: self.cur_token.literal.clone(),
+            value: self.cur_token.literal.to_string(),
+            span: self.cur_token.span.clone(),
         });
     }
 
     fn parse_boolean(&mut self) -> ast::Expression {
         return ast::Expression::Boolean {
             value: self.cur_token_is(&token::TokenType::TRUE),
+            span: self.cur_token.span.clone(),
         };
     }
 
@@ -513,8 +1284,12 @@ impl Parser {
     }
 
     fn parse_array_literal(&mut self) -> Option<ast::Expression> {
+        let start = self.cur_token.span.clone();
         if let Some(elements) = self.parse_expression_list(token::TokenType::RBRACKET) {
-            return Some(ast::Expression::ArrayLiteral { elements });
+            return Some(ast::Expression::ArrayLiteral {
+                elements,
+                span: start.to(&self.cur_token.span),
+            });
         } else {
             return None;
         }
@@ -536,13 +1311,6 @@ impl Parser {
         }
     }
 
-    fn no_prefix_parse_fn_error(&mut self) {
-        self.errors.push(format!(
-            "no prefix parse function for {:?} found",
-            self.cur_token.token_type
-        ));
-    }
-
     fn peek_precedence(&mut self) -> Precedence {
         return token_type_to_precedence(&self.peek_token.token_type);
     }
@@ -585,6 +1353,33 @@ let foobar = 838383;
         }
     }
 
+    #[test]
+    fn test_let_patterns() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("let [a, b] = xs;", "let [a, b] = xs;"),
+                ("let [first, ...others] = xs;", "let [first, ...others] = xs;"),
+                ("let [...all] = xs;", "let [...all] = xs;"),
+                ("let [[a, b], c] = xs;", "let [[a, b], c] = xs;"),
+                ("let [] = xs;", "let [] = xs;"),
+                ("let {name, age} = person;", "let {name, age} = person;"),
+                ("let [{x}, [y]] = points;", "let [{x}, [y]] = points;"),
+                ("let {name: n, age} = person;", "let {name: n, age} = person;"),
+                ("let {point: [x, y]} = shape;", "let {point: [x, y]} = shape;"),
+            ]
+        );
+
+        for (input, expected) in tests {
+            let l = lexer::Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+
+            assert_eq!(program.statements.len(), 1);
+            assert_eq!(program.statements[0].to_string(), expected);
+        }
+    }
+
     #[test]
     fn test_return_statements() {
         let input = "
@@ -602,13 +1397,11 @@ return 993322;
 
         assert_eq!(program.statements.len(), 3);
         for stmt in program.statements {
-            assert!(
-                if let ast::Statement::ReturnStatement { return_value: _ } = stmt {
-                    true
-                } else {
-                    false
-                }
-            )
+            assert!(if let ast::Statement::ReturnStatement { .. } = stmt {
+                true
+            } else {
+                false
+            })
         }
     }
 
@@ -622,8 +1415,8 @@ return 993322;
         check_parser_errors(p);
 
         assert_eq!(program.statements.len(), 1);
-        if let ast::Statement::ExpressionStatement { expression } = &program.statements[0] {
-            if let ast::Expression::Identifier { value } = expression {
+        if let ast::Statement::ExpressionStatement { expression, .. } = &program.statements[0] {
+            if let ast::Expression::Identifier { value, .. } = expression {
                 assert_eq!(value, "foobar");
             } else {
                 panic!(
@@ -648,8 +1441,8 @@ return 993322;
         check_parser_errors(p);
 
         assert_eq!(program.statements.len(), 1);
-        if let ast::Statement::ExpressionStatement { expression } = &program.statements[0] {
-            if let ast::Expression::IntegerLiteral { value } = expression {
+        if let ast::Statement::ExpressionStatement { expression, .. } = &program.statements[0] {
+            if let ast::Expression::IntegerLiteral { value, .. } = expression {
                 assert_eq!(*value, 5 as i64);
             } else {
                 panic!(
@@ -665,6 +1458,37 @@ return 993322;
         }
     }
 
+    #[test]
+    fn test_float_literal_expression() {
+        counted_array!(
+            let tests: [(&str, f64, &str); _] = [
+                ("1.05;", 1.05, "1.05\r\n"),
+                ("1.0;", 1.0, "1.0\r\n"),
+                ("1e-3;", 0.001, "0.001\r\n"),
+                ("2.5e2;", 250.0, "250.0\r\n"),
+            ]
+        );
+
+        for t in tests {
+            let l = lexer::Lexer::new(t.0);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+
+            assert_eq!(program.statements.len(), 1);
+            if let ast::Statement::ExpressionStatement {
+                expression: ast::Expression::FloatLiteral { value, .. },
+                ..
+            } = &program.statements[0]
+            {
+                assert_eq!(*value, t.1);
+            } else {
+                panic!("program.Statement[0] is not ast.FloatLiteral.");
+            }
+            assert_eq!(format!("{}", program), t.2);
+        }
+    }
+
     #[test]
     fn test_parsing_prefix_expressions() {
         counted_array!(
@@ -682,8 +1506,11 @@ return 993322;
             check_parser_errors(p);
             assert_eq!(program.statements.len(), 1);
 
-            if let ast::Statement::ExpressionStatement { expression } = &program.statements[0] {
-                if let ast::Expression::PrefixExpression { operator, right } = expression {
+            if let ast::Statement::ExpressionStatement { expression, .. } = &program.statements[0] {
+                if let ast::Expression::PrefixExpression {
+                    operator, right, ..
+                } = expression
+                {
                     assert_eq!(operator, t.1);
                     let right_exp: ast::Expression = (**right).clone();
                     if !test_integer_literal(&right_exp, t.2) {
@@ -710,6 +1537,17 @@ return 993322;
                 ("5 < 5;", 5, "<", 5),
                 ("5 == 5;", 5, "==", 5),
                 ("5 != 5;", 5, "!=", 5),
+                ("5 <= 5;", 5, "<=", 5),
+                ("5 >= 5;", 5, ">=", 5),
+                ("5 % 5;", 5, "%", 5),
+                ("5 ** 5;", 5, "**", 5),
+                ("5 && 5;", 5, "&&", 5),
+                ("5 || 5;", 5, "||", 5),
+                ("5 & 5;", 5, "&", 5),
+                ("5 | 5;", 5, "|", 5),
+                ("5 ^ 5;", 5, "^", 5),
+                ("5 << 5;", 5, "<<", 5),
+                ("5 >> 5;", 5, ">>", 5),
             ]
         );
         for t in infix_tests {
@@ -720,11 +1558,12 @@ return 993322;
             check_parser_errors(p);
             assert_eq!(program.statements.len(), 1);
 
-            if let ast::Statement::ExpressionStatement { expression } = &program.statements[0] {
+            if let ast::Statement::ExpressionStatement { expression, .. } = &program.statements[0] {
                 if let ast::Expression::InfixExpression {
                     left,
                     operator,
                     right,
+                    ..
                 } = expression
                 {
                     assert_eq!(operator, t.2);
@@ -776,6 +1615,21 @@ return 993322;
                 ("add(a + b + c * d / f + g)", "add((((a + b) + ((c * d) / f)) + g))\r\n"),
                 ("a * [1, 2, 3, 4][b * c] * d", "((a * ([1, 2, 3, 4])[(b * c)]) * d)\r\n"),
                 ("add(a * b[2], b[1], 2 * [1, 2][1])", "add((a * (b)[2]), (b)[1], (2 * ([1, 2])[1]))\r\n"),
+                ("a || b && c", "(a || (b && c))\r\n"),
+                ("a && b || c && d", "((a && b) || (c && d))\r\n"),
+                ("a < b && c >= d", "((a < b) && (c >= d))\r\n"),
+                ("a == b || !c", "((a == b) || (!c))\r\n"),
+                ("a | b ^ c & d", "(a | (b ^ (c & d)))\r\n"),
+                ("a & b == c", "((a & b) == c)\r\n"),
+                ("1 << 2 + 3", "(1 << (2 + 3))\r\n"),
+                ("a + b % c", "(a + (b % c))\r\n"),
+                ("2 ** 3 ** 2", "(2 ** (3 ** 2))\r\n"),
+                ("-2 ** 2", "(-(2 ** 2))\r\n"),
+                ("2 * 3 ** 2", "(2 * (3 ** 2))\r\n"),
+                ("x = a + b", "x = (a + b)\r\n"),
+                ("x += a * b", "x += (a * b)\r\n"),
+                ("x[i] -= 1", "(x)[i] -= 1\r\n"),
+                ("x %= y || z", "x %= (y || z)\r\n"),
             ]
         );
 
@@ -818,7 +1672,12 @@ return 993322;
             let tests: [(&str, &str); _] = [
                 ("if (x < y) { x }", "if ((x < y)) {\r\n\tx\r\n}\r\n"),
                 ("if (x < y) { x } else { y }", "if ((x < y)) {\r\n\tx\r\n} else {\r\n\ty\r\n}\r\n"),
+                ("if (x < y) { x } else if (x > y) { y }", "if ((x < y)) {\r\n\tx\r\n} else if ((x > y)) {\r\n\ty\r\n}\r\n"),
+                ("if (a) { 1 } else if (b) { 2 } else if (c) { 3 } else { 4 }", "if (a) {\r\n\t1\r\n} else if (b) {\r\n\t2\r\n} else if (c) {\r\n\t3\r\n} else {\r\n\t4\r\n}\r\n"),
                 ("while (true) {}", "while (true) {\r\n}\r\n"),
+                ("while (x) { break; continue }", "while (x) {\r\n\tbreak;\r\n\tcontinue;\r\n}\r\n"),
+                ("for (x in xs) { x }", "for (x in xs) {\r\n\tx\r\n}\r\n"),
+                ("for (k, v in h) { break; }", "for (k, v in h) {\r\n\tbreak;\r\n}\r\n"),
             ]
         );
 
@@ -843,7 +1702,8 @@ return 993322;
         check_parser_errors(p);
 
         if let ast::Statement::ExpressionStatement {
-            expression: ast::Expression::ArrayLiteral { elements },
+            expression: ast::Expression::ArrayLiteral { elements, .. },
+            ..
         } = &program.statements[0]
         {
             assert_eq!(elements.len(), 3);
@@ -865,10 +1725,11 @@ return 993322;
         check_parser_errors(p);
 
         if let ast::Statement::ExpressionStatement {
-            expression: ast::Expression::IndexExpression { left, index },
+            expression: ast::Expression::IndexExpression { left, index, .. },
+            ..
         } = &program.statements[0]
         {
-            if let ast::Expression::Identifier { value } = &**left {
+            if let ast::Expression::Identifier { value, .. } = &**left {
                 assert_eq!(value, "myArray");
             } else {
                 panic!("program.Statement[0] is not ast.Identifier. got={}", left);
@@ -905,6 +1766,10 @@ return 993322;
                 ("fn() {};", "fn () {\r\n}\r\n"),
                 ("fn(x) {};", "fn (x) {\r\n}\r\n"),
                 ("fn(x, y, z) {};", "fn (x, y, z) {\r\n}\r\n"),
+                ("fn(x, y = 2) {};", "fn (x, y = 2) {\r\n}\r\n"),
+                ("fn(x = 1 + 2) {};", "fn (x = (1 + 2)) {\r\n}\r\n"),
+                ("fn(x, ...rest) {};", "fn (x, ...rest) {\r\n}\r\n"),
+                ("fn(...xs) {};", "fn (...xs) {\r\n}\r\n"),
             ]
         );
 
@@ -919,6 +1784,95 @@ return 993322;
         }
     }
 
+    #[test]
+    fn test_function_declaration() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("fn add(x, y) { x + y }", "fn add(x, y) {\r\n\t(x + y)\r\n}\r\n"),
+                ("fn f() {};", "fn f() {\r\n}\r\n"),
+                ("fn f(x = 1, ...xs) {}", "fn f(x = 1, ...xs) {\r\n}\r\n"),
+                ("fn(x) { x }(1)", "fn (x) {\r\n\tx\r\n}(1)\r\n"),
+                ("fn f() {} fn g() {}", "fn f() {\r\n}\r\nfn g() {\r\n}\r\n"),
+            ]
+        );
+
+        for t in tests {
+            let l = lexer::Lexer::new(t.0);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+
+            assert_eq!(format!("{}", program), t.1);
+        }
+    }
+
+    #[test]
+    fn test_match_expression() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                (
+                    "match (x) { 1 => \"one\", _ => \"many\" }",
+                    "match (x) { 1 => \"one\", _ => \"many\" }",
+                ),
+                (
+                    "match (x) { 1 => \"one\", _ => \"many\", }",
+                    "match (x) { 1 => \"one\", _ => \"many\" }",
+                ),
+                (
+                    "match (p) { [a, ...rest] if a > 0 => a, {kind: \"circle\", r} => r }",
+                    "match (p) { [a, ...rest] if (a > 0) => a, {kind: \"circle\", r} => r }",
+                ),
+                (
+                    "match (n) { -1 => { 0 } -2.5 => { 1 } true => 2 }",
+                    "match (n) { (-1) => {\r\n\t0\r\n}, (-2.5) => {\r\n\t1\r\n}, true => 2 }",
+                ),
+                ("match (x) {}", "match (x) {  }"),
+            ]
+        );
+
+        for (input, expected) in tests {
+            let l = lexer::Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+
+            assert_eq!(program.statements.len(), 1);
+            assert_eq!(program.statements[0].to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_try_expression() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                (
+                    "try { f() } catch (e) { e }",
+                    "try {\r\n\tf()\r\n} catch (e) {\r\n\te\r\n}",
+                ),
+                (
+                    "try { f() } finally { g() }",
+                    "try {\r\n\tf()\r\n} finally {\r\n\tg()\r\n}",
+                ),
+                (
+                    "try {} catch ({message, kind}) {} finally {}",
+                    "try {\r\n} catch ({message, kind}) {\r\n} finally {\r\n}",
+                ),
+                ("throw \"boom\";", "throw \"boom\";"),
+                ("throw x + 1", "throw (x + 1);"),
+            ]
+        );
+
+        for (input, expected) in tests {
+            let l = lexer::Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+            check_parser_errors(p);
+
+            assert_eq!(program.statements.len(), 1);
+            assert_eq!(program.statements[0].to_string(), expected);
+        }
+    }
+
     #[test]
     fn test_call_function() {
         counted_array!(
@@ -926,6 +1880,9 @@ return 993322;
                 ("add()", "add()\r\n"),
                 ("sum(x);", "sum(x)\r\n"),
                 ("get(x, y, z)", "get(x, y, z)\r\n"),
+                ("f(1, y: 2)", "f(1, y: 2)\r\n"),
+                ("f(x: a + b, y: [])", "f(x: (a + b), y: [])\r\n"),
+                ("f({a: 1})", "f({ a: 1 })\r\n"),
             ]
         );
 
@@ -952,16 +1909,17 @@ return 993322;
         check_parser_errors(p);
 
         if let ast::Statement::ExpressionStatement {
-            expression: ast::Expression::HashLiteral { pairs },
+            expression: ast::Expression::HashLiteral { pairs, .. },
+            ..
         } = &program.statements[0]
         {
             for (i, (key, v)) in pairs.iter().enumerate() {
-                if let ast::Expression::StringLiteral { value } = key {
+                if let ast::Expression::StringLiteral { value, .. } = key {
                     assert_eq!(value, keys[i]);
                 } else {
                     panic!();
                 }
-                if let ast::Expression::IntegerLiteral { value } = v {
+                if let ast::Expression::IntegerLiteral { value, .. } = v {
                     assert_eq!(value, &values[i]);
                 } else {
                     panic!();
@@ -986,11 +1944,12 @@ return 993322;
         check_parser_errors(p);
 
         if let ast::Statement::ExpressionStatement {
-            expression: ast::Expression::HashLiteral { pairs },
+            expression: ast::Expression::HashLiteral { pairs, .. },
+            ..
         } = &program.statements[0]
         {
             for (i, (key, v)) in pairs.iter().enumerate() {
-                if let ast::Expression::StringLiteral { value } = key {
+                if let ast::Expression::StringLiteral { value, .. } = key {
                     assert_eq!(value, keys[i]);
                 } else {
                     panic!();
@@ -1012,7 +1971,8 @@ return 993322;
         check_parser_errors(p);
 
         if let ast::Statement::ExpressionStatement {
-            expression: ast::Expression::HashLiteral { pairs },
+            expression: ast::Expression::HashLiteral { pairs, .. },
+            ..
         } = &program.statements[0]
         {
             if pairs.len() != 0 {
@@ -1023,9 +1983,215 @@ return 993322;
         }
     }
 
+    #[test]
+    fn test_error_positions() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("let = 5;", "1:5: expected identifier, found `=`"),
+                ("let x = 1;\nadd(x, y", "2:9: expected `)`, found end of input"),
+                ("let x = 1;\n  ]", "2:3: expected an expression, found `]`"),
+                ("break;", "1:1: `break` outside of a loop"),
+                ("if (x) { continue; }", "1:10: `continue` outside of a loop"),
+                ("while (x) { fn() { break; } }", "1:20: `break` outside of a loop"),
+                ("for (1 in xs) {}", "1:6: expected identifier, found integer"),
+                ("for (a, b, c in xs) {}", "1:10: expected `in`, found `,`"),
+                ("let f = fn() {\n  1", "2:4: expected `}`, found end of input"),
+                ("let [a, ...b, c] = xs;", "1:13: expected `]`, found `,`"),
+                ("let [a, 1] = xs;", "1:9: expected identifier, found integer"),
+                ("fn(1) {}", "1:4: expected identifier, found integer"),
+                ("fn(x + 1) {}", "1:6: expected `)`, found `+`"),
+                ("fn(...xs, y) {}", "1:9: expected `)`, found `,`"),
+                ("f(y: 1, 2)", "1:9: positional argument after named arguments"),
+                ("fn add x) {}", "1:8: expected `(`, found identifier"),
+                ("match (x) { 1 + 2 => 3 }", "1:15: expected `=>`, found `+`"),
+                ("match (x) { 1 => 2 3 => 4 }", "1:20: expected `}`, found integer"),
+                ("match (x) { - a => 1 }", "1:15: expected integer, found identifier"),
+                ("let [1] = xs;", "1:6: expected identifier, found integer"),
+                ("while (x) { fn f() { break; } }", "1:22: `break` outside of a loop"),
+                ("let {a, [b]} = h;", "1:9: expected identifier, found `[`"),
+                ("try { 1 }", "1:10: expected `catch`, found end of input"),
+                ("try { 1 } catch e { e }", "1:17: expected `(`, found identifier"),
+                ("try 1 catch (e) {}", "1:5: expected `{`, found integer"),
+                ("throw;", "1:6: expected an expression, found `;`"),
+            ]
+        );
+
+        for t in tests {
+            let l = lexer::Lexer::new(t.0);
+            let mut p = Parser::new(l);
+            p.parse_program();
+
+            assert_eq!(error_messages(&p), vec![t.1], "{}", t.0);
+        }
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        counted_array!(
+            let tests: [(&str, usize, Vec<&str>); _] = [
+                (
+                    "let = 5;\nlet y = ;\nadd(1, 2\nlet z = 3;",
+                    1,
+                    vec![
+                        "1:5: expected identifier, found `=`",
+                        "2:9: expected an expression, found `;`",
+                        "4:1: expected `)`, found `let`",
+                    ],
+                ),
+                (
+                    "let f = fn(x) {\n  let = x;\n  x +;\n  x\n};\nf(1)",
+                    2,
+                    vec![
+                        "2:7: expected identifier, found `=`",
+                        "3:6: expected an expression, found `;`",
+                    ],
+                ),
+                (
+                    "if (x { 1 }\nlet y = 2;",
+                    1,
+                    vec!["1:7: expected `)`, found `{`"],
+                ),
+                (
+                    "while (true) { let x = }\nlet y = (1 + 2;\ny",
+                    2,
+                    vec![
+                        "1:24: expected an expression, found `}`",
+                        "2:15: expected `)`, found `;`",
+                    ],
+                ),
+                (
+                    "let y = match (x) { {a: } => 1, _ => 2 };\nlet z = ;\nz",
+                    1,
+                    vec![
+                        "1:25: expected identifier, found `}`",
+                        "2:9: expected an expression, found `;`",
+                    ],
+                ),
+                (
+                    "let {a: {b: }} = h;\nlet {c, = h;\nlet d = 1;",
+                    1,
+                    vec![
+                        "1:13: expected identifier, found `}`",
+                        "2:9: expected identifier, found `=`",
+                    ],
+                ),
+                (
+                    "let = 1\nfn(a) { a }(2)\nlet = 2\nmatch (x) { _ => 1 }",
+                    2,
+                    vec![
+                        "1:5: expected identifier, found `=`",
+                        "3:5: expected identifier, found `=`",
+                    ],
+                ),
+                (
+                    "let s = \"a\\q\";\nlet n = 99999999999999999999;\nbreak;\ns",
+                    2,
+                    vec![
+                        "1:11: unknown escape sequence \\q",
+                        "2:9: could not parse 99999999999999999999 as integer",
+                        "3:1: `break` outside of a loop",
+                    ],
+                ),
+            ]
+        );
+
+        for (input, statements, errors) in tests {
+            let l = lexer::Lexer::new(input);
+            let mut p = Parser::new(l);
+            let program = p.parse_program();
+
+            assert_eq!(error_messages(&p), errors, "{}", input);
+            assert_eq!(program.statements.len(), statements, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_outcome() {
+        counted_array!(
+            let tests: [(&str, &str); _] = [
+                ("let x = 5;", "complete"),
+                ("", "complete"),
+                ("let f = fn(x) {", "incomplete"),
+                ("if (x) { 1 } else {\n  2", "incomplete"),
+                ("add(1, 2", "incomplete"),
+                ("add(1,", "incomplete"),
+                ("[1, 2", "incomplete"),
+                ("{\"a\": 1", "incomplete"),
+                ("(1 + 2", "incomplete"),
+                ("let x = 1 +", "incomplete"),
+                ("let s = \"abc", "incomplete"),
+                ("let x = 1; /* comment", "incomplete"),
+                ("let = 5; let f = fn() {", "invalid"),
+                ("add(1, 2]", "invalid"),
+                ("let x = 5 }", "invalid"),
+            ]
+        );
+
+        for (input, expected) in tests {
+            let l = lexer::Lexer::new(input);
+            let outcome = match Parser::new(l).parse() {
+                ParseOutcome::Complete(_) => "complete",
+                ParseOutcome::Incomplete(_) => "incomplete",
+                ParseOutcome::Invalid(_) => "invalid",
+            };
+            assert_eq!(outcome, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_error_fields() {
+        let l = lexer::Lexer::new("let x 5;");
+        let mut p = Parser::new(l);
+        p.parse_program();
+
+        assert_eq!(p.errors.len(), 1);
+        let err = &p.errors[0];
+        assert_eq!(err.kind, ParseErrorKind::UnexpectedToken);
+        assert_eq!(err.expected, Some(token::TokenType::ASSIGN));
+        assert_eq!(err.found, Some(token::TokenType::INT));
+        assert_eq!((err.span.start.line, err.span.start.column), (1, 7));
+    }
+
+    #[test]
+    fn test_lexer_errors() {
+        let input = "let x = 5;\n/* never closed\nlet y = 6;";
+
+        let l = lexer::Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+
+        assert_eq!(program.statements.len(), 1);
+        assert_eq!(error_messages(&p), vec!["2:1: unterminated block comment"]);
+    }
+
+    #[test]
+    fn test_expression_spans() {
+        let input = "let x = 1;\nx + add(2, 3);";
+
+        let l = lexer::Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program();
+        check_parser_errors(p);
+
+        let span = program.statements[1].span();
+        assert_eq!((span.start.line, span.start.column), (2, 1));
+        assert_eq!((span.start.offset, span.end.offset), (11, 25));
+        if let ast::Statement::ExpressionStatement {
+            expression: ast::Expression::InfixExpression { right, .. },
+            ..
+        } = &program.statements[1]
+        {
+            let span = right.span();
+            assert_eq!((span.start.line, span.start.column), (2, 5));
+            assert_eq!((span.start.offset, span.end.offset), (15, 24));
+        } else {
+            panic!();
+        }
+    }
+
     fn test_let_statement(stmt: &ast::Statement, t: &str) {
-        if let ast::Statement::LetStatement { name, value: _ } = stmt {
-            if let ast::Expression::Identifier { value } = name {
+        if let ast::Statement::LetStatement { name, .. } = stmt {
+            if let ast::Expression::Identifier { value, .. } = name {
                 assert_eq!(value, t);
             } else {
                 panic!("expression does not equal to identifier.");
@@ -1036,7 +2202,7 @@ return 993322;
     }
 
     fn test_integer_literal(il: &ast::Expression, value: i64) -> bool {
-        if let ast::Expression::IntegerLiteral { value: integ } = il {
+        if let ast::Expression::IntegerLiteral { value: integ, .. } = il {
             assert_eq!(*integ, value);
             return true;
         } else {
@@ -1049,6 +2215,7 @@ return 993322;
             left,
             operator,
             right,
+            ..
         } = expression
         {
             assert_eq!(operator, op);
@@ -1067,7 +2234,11 @@ return 993322;
         }
     }
 
-    fn check_parser_errors(p: Parser) {
+    fn error_messages(p: &Parser<'_>) -> Vec<String> {
+        p.errors.iter().map(|err| err.to_string()).collect()
+    }
+
+    fn check_parser_errors(p: Parser<'_>) {
         if p.errors.len() == 0 {
             return;
         }
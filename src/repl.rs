@@ -1,4 +1,4 @@
-use super::{evaluator, lexer, object, parser};
+use super::{diagnostics, evaluator, lexer, object, parser};
 use std::cell::RefCell;
 use std::cmp;
 use std::io::{stdin, stdout, Write};
@@ -10,6 +10,7 @@ use termion::raw::IntoRawMode;
 
 pub struct Repl {
     evaluator: RefCell<evaluator::Evaluator>,
+    renderer: diagnostics::Renderer,
     commands: Vec<String>,
     view: Vec<Vec<char>>,
     row_offset: u16,
@@ -20,7 +21,7 @@ pub struct Repl {
 }
 
 impl Repl {
-    pub fn new() -> Self {
+    pub fn new(renderer: diagnostics::Renderer) -> Self {
         let evaluator = evaluator::Evaluator::new();
         let commands = vec![];
         let view = vec![vec![]];
@@ -28,6 +29,7 @@ impl Repl {
 
         return Repl {
             evaluator: RefCell::new(evaluator),
+            renderer,
             commands,
             view,
             row_offset: 0,
@@ -45,7 +47,9 @@ impl Repl {
         self.row_offset + self.view.len() as u16 - 1
     }
 
-    pub fn start(&mut self) {
+    // Runs until the input ends or the program calls `exit`, and returns the
+    // exit status.
+    pub fn start(&mut self) -> i32 {
         let mut stdout = stdout().into_raw_mode().unwrap();
         let stdin = stdin();
         self.fetch_row_offset();
@@ -66,14 +70,14 @@ impl Repl {
                             termion::cursor::Goto(self.last_x(), self.last_y())
                         )
                         .unwrap();
-                        let (need_next, output) = self.enter();
+                        let (need_next, output) = match self.enter() {
+                            Ok(entered) => entered,
+                            Err(code) => return code,
+                        };
                         if need_next {
                             self.set_max_view_len();
                             self.fetch_row_offset();
                         } else {
-                            if output == String::from("Exit") {
-                                return;
-                            }
                             write!(stdout, "{}\r\n", output).unwrap();
                             let (_, y) = stdout.cursor_pos().unwrap();
                             self.row_offset = y;
@@ -171,40 +175,49 @@ impl Repl {
                 self.disp(&mut stdout);
             }
         }
+        return 0;
     }
 
-    fn enter(&mut self) -> (bool, String) {
+    // Err holds the status passed to `exit`.
+    fn enter(&mut self) -> Result<(bool, String), i32> {
         let mut output = String::new();
 
         let input = self.get_command();
         let l = lexer::Lexer::new(&input);
-        let mut p = parser::Parser::new(l);
-        let program = p.parse_program();
-
-        if program.need_next() {
-            self.view.push(vec![]);
-            return (true, output);
-        }
-
-        if p.errors.len() != 0 {
-            output += "parser errors:\r\n";
-            for err in p.errors {
-                output += &format!("\t{}\r\n", err);
+        match parser::Parser::new(l).parse() {
+            parser::ParseOutcome::Incomplete(_) => {
+                self.view.push(vec![]);
+                return Ok((true, output));
+            }
+            parser::ParseOutcome::Invalid(errors) => {
+                for err in &errors {
+                    let diagnostic = diagnostics::Diagnostic::from_parse_error(err);
+                    output += &self.renderer.render(&diagnostic, None, &input);
+                }
             }
-        } else {
-            match self.evaluator.borrow_mut().eval_program(program) {
-                Some(evaluated) => {
-                    if let object::Object::Null = *evaluated {
-                    } else {
-                        output += &evaluated.string();
+            parser::ParseOutcome::Complete(program) => {
+                match self.evaluator.borrow_mut().eval_program(program) {
+                    evaluator::Signal::Value(evaluated) => {
+                        if let object::Object::Null = *evaluated {
+                        } else {
+                            output += &evaluated.string();
+                        }
                     }
+                    evaluator::Signal::Throw(error) => {
+                        if let Some(diagnostic) = diagnostics::Diagnostic::from_object(&error) {
+                            output += &self.renderer.render(&diagnostic, None, &input)
+                        }
+                    }
+                    evaluator::Signal::Exit(code) => return Err(code),
+                    _ => {}
                 }
-                None => output += "cannot evaluate error!",
             }
         }
+        // The terminal is in raw mode.
+        let output = output.trim_end().replace('\n', "\r\n");
 
         self.new_line(input);
-        return (false, output);
+        return Ok((false, output));
     }
 
     fn set_max_view_len(&mut self) {
@@ -0,0 +1,44 @@
+use super::{diagnostics, evaluator, lexer, parser};
+use std::fs::read_to_string;
+
+// Runs a script file and returns the process exit status. Diagnostics go to
+// stderr; the script's own output comes from `puts`.
+pub fn run_file(path: &str, renderer: &diagnostics::Renderer) -> i32 {
+    let source = match read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            let diagnostic = diagnostics::Diagnostic {
+                message: format!("could not read {}: {}", path, err),
+                span: None,
+                help: None,
+                notes: Vec::new(),
+            };
+            eprint!("{}", renderer.render(&diagnostic, Some(path), ""));
+            return 1;
+        }
+    };
+
+    let l = lexer::Lexer::new_with_file(&source, path);
+    let program = match parser::Parser::new(l).parse() {
+        parser::ParseOutcome::Complete(program) => program,
+        parser::ParseOutcome::Incomplete(errors) | parser::ParseOutcome::Invalid(errors) => {
+            for err in &errors {
+                let diagnostic = diagnostics::Diagnostic::from_parse_error(err);
+                eprint!("{}", renderer.render(&diagnostic, Some(path), &source));
+            }
+            return 1;
+        }
+    };
+
+    let mut evaluator = evaluator::Evaluator::new();
+    match evaluator.eval_program(program) {
+        evaluator::Signal::Exit(code) => code,
+        evaluator::Signal::Throw(error) => {
+            if let Some(diagnostic) = diagnostics::Diagnostic::from_object(&error) {
+                eprint!("{}", renderer.render(&diagnostic, Some(path), &source));
+            }
+            1
+        }
+        _ => 0,
+    }
+}
@@ -1,4 +1,6 @@
+use std::borrow::Cow;
 use std::fmt;
+use std::rc::Rc;
 
 #[allow(dead_code)]
 #[derive(PartialOrd, PartialEq, Debug, Clone, Eq, Hash)]
@@ -8,24 +10,44 @@ pub enum TokenType {
 
     IDENT,
     INT,
+    FLOAT,
     STRING,
 
     ASSIGN,
+    PLUSASSIGN,
+    MINUSASSIGN,
+    ASTERISKASSIGN,
+    SLASHASSIGN,
+    PERCENTASSIGN,
     PLUS,
     MINUS,
     BANG,
     ASTERISK,
     SLASH,
+    PERCENT,
+    POWER,
 
     LT,
     GT,
+    LTEQ,
+    GTEQ,
     EQ,
     NOTEQ,
 
+    AND,
+    OR,
+    AMPERSAND,
+    PIPE,
+    CARET,
+    LSHIFT,
+    RSHIFT,
+
     COMMA,
     SEMICOLON,
     COLON,
     DOT,
+    ELLIPSIS,
+    ARROW,
 
     LPAREN,
     RPAREN,
@@ -42,26 +64,139 @@ pub enum TokenType {
     ELSE,
     RETURN,
     WHILE,
+    BREAK,
+    CONTINUE,
+    FOR,
+    IN,
+    MATCH,
+    TRY,
+    CATCH,
+    FINALLY,
+    THROW,
 }
 
+// How a token is named in messages meant for users.
 impl fmt::Display for TokenType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+        let s = match self {
+            TokenType::ILLEGAL => return write!(f, "illegal character"),
+            TokenType::EOF => return write!(f, "end of input"),
+            TokenType::IDENT => return write!(f, "identifier"),
+            TokenType::INT => return write!(f, "integer"),
+            TokenType::FLOAT => return write!(f, "float"),
+            TokenType::STRING => return write!(f, "string"),
+            TokenType::ASSIGN => "=",
+            TokenType::PLUSASSIGN => "+=",
+            TokenType::MINUSASSIGN => "-=",
+            TokenType::ASTERISKASSIGN => "*=",
+            TokenType::SLASHASSIGN => "/=",
+            TokenType::PERCENTASSIGN => "%=",
+            TokenType::PLUS => "+",
+            TokenType::MINUS => "-",
+            TokenType::BANG => "!",
+            TokenType::ASTERISK => "*",
+            TokenType::SLASH => "/",
+            TokenType::PERCENT => "%",
+            TokenType::POWER => "**",
+            TokenType::LT => "<",
+            TokenType::GT => ">",
+            TokenType::LTEQ => "<=",
+            TokenType::GTEQ => ">=",
+            TokenType::EQ => "==",
+            TokenType::NOTEQ => "!=",
+            TokenType::AND => "&&",
+            TokenType::OR => "||",
+            TokenType::AMPERSAND => "&",
+            TokenType::PIPE => "|",
+            TokenType::CARET => "^",
+            TokenType::LSHIFT => "<<",
+            TokenType::RSHIFT => ">>",
+            TokenType::COMMA => ",",
+            TokenType::SEMICOLON => ";",
+            TokenType::COLON => ":",
+            TokenType::DOT => ".",
+            TokenType::ELLIPSIS => "...",
+            TokenType::ARROW => "=>",
+            TokenType::LPAREN => "(",
+            TokenType::RPAREN => ")",
+            TokenType::LBRACE => "{",
+            TokenType::RBRACE => "}",
+            TokenType::LBRACKET => "[",
+            TokenType::RBRACKET => "]",
+            TokenType::FUNCTION => "fn",
+            TokenType::LET => "let",
+            TokenType::TRUE => "true",
+            TokenType::FALSE => "false",
+            TokenType::IF => "if",
+            TokenType::ELSE => "else",
+            TokenType::RETURN => "return",
+            TokenType::WHILE => "while",
+            TokenType::BREAK => "break",
+            TokenType::CONTINUE => "continue",
+            TokenType::FOR => "for",
+            TokenType::IN => "in",
+            TokenType::MATCH => "match",
+            TokenType::TRY => "try",
+            TokenType::CATCH => "catch",
+            TokenType::FINALLY => "finally",
+            TokenType::THROW => "throw",
+        };
+        write!(f, "`{}`", s)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Default, Eq, Hash)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Default, Eq, Hash)]
+pub struct Span {
+    pub file: Option<Rc<str>>,
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            file: self.file.clone(),
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}", file, self.start),
+            None => write!(f, "{}", self.start),
+        }
     }
 }
 
 #[derive(Clone)]
-pub struct Token {
+pub struct Token<'a> {
     pub token_type: TokenType,
-    pub literal: String,
+    pub literal: Cow<'a, str>,
+    pub span: Span,
 }
 
-impl fmt::Display for Token {
+impl fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "token_type: {:?}, literal: {}",
-            self.token_type, self.literal
+            "token_type: {:?}, literal: {}, span: {}",
+            self.token_type, self.literal, self.span
         )
     }
 }
@@ -76,13 +211,23 @@ pub fn lookup_ident(ident: &str) -> TokenType {
         "else" => TokenType::ELSE,
         "return" => TokenType::RETURN,
         "while" => TokenType::WHILE,
+        "break" => TokenType::BREAK,
+        "continue" => TokenType::CONTINUE,
+        "for" => TokenType::FOR,
+        "in" => TokenType::IN,
+        "match" => TokenType::MATCH,
+        "try" => TokenType::TRY,
+        "catch" => TokenType::CATCH,
+        "finally" => TokenType::FINALLY,
+        "throw" => TokenType::THROW,
         _ => TokenType::IDENT,
     }
 }
 
-pub fn new_token(token_type: TokenType, literal: String) -> Token {
-    return Token {
+pub fn new_token(token_type: TokenType, literal: Cow<'_, str>, span: Span) -> Token<'_> {
+    Token {
         token_type,
-        literal: literal,
-    };
+        literal,
+        span,
+    }
 }